@@ -20,12 +20,85 @@ impl GMetadata for ProgramMetadata {
 
 #[derive(Default, Encode, Decode, TypeInfo)]
 pub struct AttributeStore {
-    admin: ActorId,
-    ft_contract_id: ActorId,
-    attributes: BTreeMap<AttributeId, (AttrMetadata, Price)>,
-    owners: BTreeMap<TamagotchiId, BTreeSet<AttributeId>>,
-    transaction_id: TransactionId,
-    transactions: BTreeMap<TamagotchiId, (TransactionId, AttributeId)>,
+    pub admin: ActorId,
+    pub ft_contract_id: ActorId,
+    pub attributes: BTreeMap<AttributeId, (AttrMetadata, Price)>,
+    pub owners: BTreeMap<TamagotchiId, BTreeSet<AttributeId>>,
+    pub transaction_id: TransactionId,
+    pub transactions: BTreeMap<TamagotchiId, (TransactionId, AttributeId)>,
+    pub equipped: BTreeMap<TamagotchiId, BTreeMap<EquipmentSlot, AttributeId>>,
+    pub approvals: BTreeMap<(ActorId, AttributeId), ActorId>,
+}
+
+impl AttributeStore {
+    /// Returns the attributes owned by the Tamagotchi.
+    pub fn attributes_of(&self, tmg_id: &TamagotchiId) -> BTreeSet<AttributeId> {
+        self.owners.get(tmg_id).cloned().unwrap_or_default()
+    }
+
+    /// Returns the metadata and the price of the attribute.
+    pub fn attribute(&self, attribute_id: AttributeId) -> Option<(AttrMetadata, Price)> {
+        self.attributes.get(&attribute_id).cloned()
+    }
+
+    /// Returns all attributes that can be bought in the store with their prices.
+    pub fn catalog(&self) -> Vec<(AttributeId, AttrMetadata, Price)> {
+        self.attributes
+            .iter()
+            .map(|(id, (metadata, price))| (*id, metadata.clone(), *price))
+            .collect()
+    }
+
+    /// Returns the uncompleted purchase of the Tamagotchi (if any).
+    pub fn pending_transaction(
+        &self,
+        tmg_id: &TamagotchiId,
+    ) -> Option<(TransactionId, AttributeId)> {
+        self.transactions.get(tmg_id).copied()
+    }
+
+    /// Returns the attributes the Tamagotchi wears in each slot.
+    pub fn equipped_of(&self, tmg_id: &TamagotchiId) -> BTreeMap<EquipmentSlot, AttributeId> {
+        self.equipped.get(tmg_id).cloned().unwrap_or_default()
+    }
+
+    /// Returns the combined effects of the attributes the Tamagotchi has equipped.
+    pub fn effects_of(&self, tmg_id: &TamagotchiId) -> Effects {
+        combined_effects(&self.attributes, self.equipped.get(tmg_id))
+    }
+
+    /// Returns the account allowed to transfer the attribute from the holder (if any).
+    pub fn approval_of(&self, holder: &ActorId, attribute_id: AttributeId) -> Option<ActorId> {
+        self.approvals.get(&(*holder, attribute_id)).copied()
+    }
+
+    /// Returns the uncompleted purchases of all Tamagotchis.
+    pub fn pending_transactions(&self) -> Vec<(TamagotchiId, TransactionId, AttributeId)> {
+        self.transactions
+            .iter()
+            .map(|(tmg_id, (transaction_id, attribute_id))| {
+                (*tmg_id, *transaction_id, *attribute_id)
+            })
+            .collect()
+    }
+}
+
+/// Combines the effects of the equipped attributes.
+/// Shared by the store program and its state so that they can't differ.
+pub fn combined_effects(
+    attributes: &BTreeMap<AttributeId, (AttrMetadata, Price)>,
+    equipped: Option<&BTreeMap<EquipmentSlot, AttributeId>>,
+) -> Effects {
+    let mut effects = Effects::default();
+    for attribute_id in equipped.into_iter().flat_map(|equipped| equipped.values()) {
+        if let Some((metadata, _)) = attributes.get(attribute_id) {
+            metadata
+                .effects
+                .iter()
+                .for_each(|effect| effects.apply(effect));
+        }
+    }
+    effects
 }
 
 #[derive(Encode, Decode, Clone, TypeInfo, Debug)]
@@ -33,6 +106,66 @@ pub struct AttrMetadata {
     pub title: String,
     pub description: String,
     pub media: String,
+    /// The slot the attribute occupies when equipped.
+    /// Attributes without a slot can't be equipped.
+    pub slot: Option<EquipmentSlot>,
+    /// What the attribute does in the game when equipped.
+    pub effects: Vec<AttrEffect>,
+}
+
+#[derive(Encode, Decode, Clone, Copy, TypeInfo, Debug, PartialEq, Eq)]
+pub enum AttrEffect {
+    /// Multiplies the damage dealt by the Tamagotchi (in percent, `100` leaves it unchanged).
+    AttackMultiplier(u16),
+    /// Reduces the damage received by the Tamagotchi.
+    Defense(u16),
+    /// Increases the energy the Tamagotchi starts the battle with.
+    EnergyBonus(u16),
+    /// Restores the Tamagotchi energy at the beginning of each round.
+    Heal(u16),
+}
+
+/// Effects of several attributes combined together.
+#[derive(Encode, Decode, Clone, Copy, TypeInfo, Debug, PartialEq, Eq)]
+pub struct Effects {
+    pub attack_multiplier: u16,
+    pub defense: u16,
+    pub energy_bonus: u16,
+    pub heal: u16,
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self {
+            attack_multiplier: 100,
+            defense: 0,
+            energy_bonus: 0,
+            heal: 0,
+        }
+    }
+}
+
+impl Effects {
+    pub fn apply(&mut self, effect: &AttrEffect) {
+        match *effect {
+            AttrEffect::AttackMultiplier(multiplier) => {
+                let attack_multiplier = self.attack_multiplier as u32 * multiplier as u32 / 100;
+                self.attack_multiplier = attack_multiplier.min(u16::MAX as u32) as u16;
+            }
+            AttrEffect::Defense(defense) => self.defense = self.defense.saturating_add(defense),
+            AttrEffect::EnergyBonus(bonus) => {
+                self.energy_bonus = self.energy_bonus.saturating_add(bonus)
+            }
+            AttrEffect::Heal(heal) => self.heal = self.heal.saturating_add(heal),
+        }
+    }
+}
+
+#[derive(Encode, Decode, Clone, Copy, TypeInfo, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EquipmentSlot {
+    Weapon,
+    Hat,
+    Armour,
 }
 
 #[derive(Encode, Decode, TypeInfo, Debug)]
@@ -54,14 +187,214 @@ pub enum StoreAction {
     RemoveTx {
         tamagotchi_id: TamagotchiId,
     },
+    /// Puts the attribute into its slot replacing the previously equipped one.
+    /// Can be called by the Tamagotchi owner or by the Tamagotchi itself.
+    Equip {
+        tamagotchi_id: TamagotchiId,
+        attribute_id: AttributeId,
+    },
+    Unequip {
+        tamagotchi_id: TamagotchiId,
+        slot: EquipmentSlot,
+    },
+    GetEquipped {
+        tamagotchi_id: TamagotchiId,
+    },
+    /// Returns the combined effects of the equipped attributes.
+    GetEffects {
+        tamagotchi_id: TamagotchiId,
+    },
+    /// Allows `spender` to transfer the attribute from `holder` once.
+    /// The attribute is unequipped and can't be equipped or transferred by anyone else
+    /// until the spender transfers or releases it.
+    /// Can be called by the holder or by the owner of the holder Tamagotchi.
+    ApproveAttribute {
+        holder: ActorId,
+        attribute_id: AttributeId,
+        spender: ActorId,
+    },
+    /// Moves the attribute from `from` to `to` unequipping it.
+    /// If `to` already has that attribute, it's only taken from `from`.
+    /// Can be called by `from`, by the owner of the `from` Tamagotchi
+    /// or by the account approved with `ApproveAttribute`.
+    TransferAttribute {
+        from: ActorId,
+        to: ActorId,
+        attribute_id: AttributeId,
+    },
+    /// Cancels the approval of the attribute leaving it to `holder`.
+    /// Can be called by the approved account only.
+    ReleaseAttribute {
+        holder: ActorId,
+        attribute_id: AttributeId,
+    },
+    GetApproval {
+        holder: ActorId,
+        attribute_id: AttributeId,
+    },
 }
 
 #[derive(Encode, Decode, TypeInfo)]
 pub enum StoreEvent {
-    AttributeCreated { attribute_id: AttributeId },
-    AttributeSold { success: bool },
-    Attributes { attributes: BTreeSet<AttributeId> },
-    CompletePrevTx { attribute_id: AttributeId },
-    FtContractIdSet { ft_contract_id: ActorId },
-    TxRemoved { tamagotchi_id: ActorId },
+    AttributeCreated {
+        attribute_id: AttributeId,
+    },
+    AttributeSold {
+        success: bool,
+    },
+    Attributes {
+        attributes: BTreeSet<AttributeId>,
+    },
+    CompletePrevTx {
+        attribute_id: AttributeId,
+    },
+    FtContractIdSet {
+        ft_contract_id: ActorId,
+    },
+    TxRemoved {
+        tamagotchi_id: ActorId,
+    },
+    AttributeEquipped {
+        tamagotchi_id: TamagotchiId,
+        slot: EquipmentSlot,
+        attribute_id: AttributeId,
+    },
+    AttributeUnequipped {
+        tamagotchi_id: TamagotchiId,
+        slot: EquipmentSlot,
+    },
+    Equipped {
+        equipped: BTreeMap<EquipmentSlot, AttributeId>,
+    },
+    Effects {
+        effects: Effects,
+    },
+    AttributeApproved {
+        holder: ActorId,
+        attribute_id: AttributeId,
+        spender: ActorId,
+    },
+    AttributeTransferred {
+        from: ActorId,
+        to: ActorId,
+        attribute_id: AttributeId,
+    },
+    AttributeReleased {
+        holder: ActorId,
+        attribute_id: AttributeId,
+    },
+    Approval {
+        spender: Option<ActorId>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribute(slot: EquipmentSlot, effects: Vec<AttrEffect>) -> (AttrMetadata, Price) {
+        let metadata = AttrMetadata {
+            title: String::new(),
+            description: String::new(),
+            media: String::new(),
+            slot: Some(slot),
+            effects,
+        };
+        (metadata, 0)
+    }
+
+    #[test]
+    fn nothing_equipped() {
+        let attributes = [(1, attribute(EquipmentSlot::Hat, vec![AttrEffect::Heal(5)]))].into();
+        assert_eq!(combined_effects(&attributes, None), Effects::default());
+        assert_eq!(
+            combined_effects(&attributes, Some(&BTreeMap::new())),
+            Effects::default()
+        );
+    }
+
+    #[test]
+    fn equipped_effects_add_up() {
+        let attributes = [
+            (
+                1,
+                attribute(
+                    EquipmentSlot::Weapon,
+                    vec![AttrEffect::AttackMultiplier(150), AttrEffect::Defense(5)],
+                ),
+            ),
+            (
+                2,
+                attribute(
+                    EquipmentSlot::Hat,
+                    vec![AttrEffect::Defense(10), AttrEffect::Heal(3)],
+                ),
+            ),
+            (
+                3,
+                attribute(EquipmentSlot::Armour, vec![AttrEffect::EnergyBonus(20)]),
+            ),
+        ]
+        .into();
+        let equipped = [(EquipmentSlot::Weapon, 1), (EquipmentSlot::Hat, 2)].into();
+        assert_eq!(
+            combined_effects(&attributes, Some(&equipped)),
+            Effects {
+                attack_multiplier: 150,
+                defense: 15,
+                energy_bonus: 0,
+                heal: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn removed_attribute_has_no_effects() {
+        let attributes = [(1, attribute(EquipmentSlot::Hat, vec![AttrEffect::Heal(5)]))].into();
+        let equipped = [(EquipmentSlot::Hat, 1), (EquipmentSlot::Weapon, 2)].into();
+        assert_eq!(
+            combined_effects(&attributes, Some(&equipped)),
+            Effects {
+                heal: 5,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn attack_multipliers_multiply() {
+        let mut effects = Effects::default();
+        effects.apply(&AttrEffect::AttackMultiplier(150));
+        effects.apply(&AttrEffect::AttackMultiplier(200));
+        assert_eq!(effects.attack_multiplier, 300);
+        effects.apply(&AttrEffect::AttackMultiplier(50));
+        assert_eq!(effects.attack_multiplier, 150);
+    }
+
+    #[test]
+    fn attack_multiplier_saturates() {
+        let mut effects = Effects::default();
+        effects.apply(&AttrEffect::AttackMultiplier(u16::MAX));
+        effects.apply(&AttrEffect::AttackMultiplier(u16::MAX));
+        assert_eq!(effects.attack_multiplier, u16::MAX);
+    }
+
+    #[test]
+    fn bonuses_saturate() {
+        let mut effects = Effects::default();
+        for _ in 0..2 {
+            effects.apply(&AttrEffect::Defense(u16::MAX));
+            effects.apply(&AttrEffect::EnergyBonus(u16::MAX));
+            effects.apply(&AttrEffect::Heal(u16::MAX));
+        }
+        assert_eq!(
+            effects,
+            Effects {
+                attack_multiplier: 100,
+                defense: u16::MAX,
+                energy_bonus: u16::MAX,
+                heal: u16::MAX,
+            }
+        );
+    }
 }
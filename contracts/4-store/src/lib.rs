@@ -3,12 +3,14 @@
 use ft_main_io::{FTokenAction, FTokenEvent, LogicAction};
 use gstd::{exec, msg, prelude::*, ActorId};
 use store_io::{
-    AttrMetadata, AttributeId, Price, StoreAction, StoreEvent, TamagotchiId, TransactionId,
+    combined_effects, AttrMetadata, AttributeId, EquipmentSlot, Price, StoreAction, StoreEvent,
+    TamagotchiId, TransactionId,
 };
+use tmg_io::{TmgAction, TmgEvent};
 
 static mut STORE: Option<AttributeStore> = None;
 
-#[derive(Default, Encode, Decode, TypeInfo)]
+#[derive(Default)]
 pub struct AttributeStore {
     admin: ActorId,
     ft_contract_id: ActorId,
@@ -16,6 +18,8 @@ pub struct AttributeStore {
     owners: BTreeMap<TamagotchiId, BTreeSet<AttributeId>>,
     transaction_id: TransactionId,
     transactions: BTreeMap<TamagotchiId, (TransactionId, AttributeId)>,
+    equipped: BTreeMap<TamagotchiId, BTreeMap<EquipmentSlot, AttributeId>>,
+    approvals: BTreeMap<(ActorId, AttributeId), ActorId>,
 }
 
 impl AttributeStore {
@@ -138,6 +142,192 @@ impl AttributeStore {
         )
         .expect("Error in sending a reply `StoreEvent::TxRemoved`");
     }
+
+    async fn equip(&mut self, tmg_id: &TamagotchiId, attribute_id: AttributeId) {
+        check_tmg_owner(tmg_id).await;
+        assert!(
+            self.owners
+                .get(tmg_id)
+                .map_or(false, |attributes| attributes.contains(&attribute_id)),
+            "The Tamagotchi doesn't own that attribute"
+        );
+        assert!(
+            !self.approvals.contains_key(&(*tmg_id, attribute_id)),
+            "The attribute is approved to be transferred"
+        );
+        let (metadata, _) = self
+            .attributes
+            .get(&attribute_id)
+            .expect("Can`t get attribute_id");
+        let slot = metadata.slot.expect("That attribute can't be equipped");
+
+        self.equipped
+            .entry(*tmg_id)
+            .or_default()
+            .insert(slot, attribute_id);
+
+        msg::reply(
+            StoreEvent::AttributeEquipped {
+                tamagotchi_id: *tmg_id,
+                slot,
+                attribute_id,
+            },
+            0,
+        )
+        .expect("Error in sending a reply `StoreEvent::AttributeEquipped`");
+    }
+
+    async fn unequip(&mut self, tmg_id: &TamagotchiId, slot: EquipmentSlot) {
+        check_tmg_owner(tmg_id).await;
+        if let Some(equipped) = self.equipped.get_mut(tmg_id) {
+            equipped.remove(&slot);
+            if equipped.is_empty() {
+                self.equipped.remove(tmg_id);
+            }
+        }
+        msg::reply(
+            StoreEvent::AttributeUnequipped {
+                tamagotchi_id: *tmg_id,
+                slot,
+            },
+            0,
+        )
+        .expect("Error in sending a reply `StoreEvent::AttributeUnequipped`");
+    }
+
+    fn get_equipped(&self, tmg_id: &TamagotchiId) {
+        let equipped = self.equipped.get(tmg_id).cloned().unwrap_or_default();
+        msg::reply(StoreEvent::Equipped { equipped }, 0)
+            .expect("Error in sending a reply `StoreEvent::Equipped`");
+    }
+
+    fn get_effects(&self, tmg_id: &TamagotchiId) {
+        let effects = combined_effects(&self.attributes, self.equipped.get(tmg_id));
+        msg::reply(StoreEvent::Effects { effects }, 0)
+            .expect("Error in sending a reply `StoreEvent::Effects`");
+    }
+
+    async fn approve_attribute(
+        &mut self,
+        holder: &ActorId,
+        attribute_id: AttributeId,
+        spender: &ActorId,
+    ) {
+        check_tmg_owner(holder).await;
+        assert!(
+            self.holds(holder, attribute_id),
+            "The holder doesn't own that attribute"
+        );
+        assert!(
+            !self.approvals.contains_key(&(*holder, attribute_id)),
+            "The attribute is already approved"
+        );
+        self.approvals.insert((*holder, attribute_id), *spender);
+        self.take_off(holder, attribute_id);
+        msg::reply(
+            StoreEvent::AttributeApproved {
+                holder: *holder,
+                attribute_id,
+                spender: *spender,
+            },
+            0,
+        )
+        .expect("Error in sending a reply `StoreEvent::AttributeApproved`");
+    }
+
+    async fn transfer_attribute(
+        &mut self,
+        from: &ActorId,
+        to: &ActorId,
+        attribute_id: AttributeId,
+    ) {
+        // the attribute has already been moved by the previous attempt
+        // of the same transfer, so the sender only needs the reply
+        if !self.holds(from, attribute_id) && self.holds(to, attribute_id) {
+            msg::reply(
+                StoreEvent::AttributeTransferred {
+                    from: *from,
+                    to: *to,
+                    attribute_id,
+                },
+                0,
+            )
+            .expect("Error in sending a reply `StoreEvent::AttributeTransferred`");
+            return;
+        }
+        if self.approvals.get(&(*from, attribute_id)) != Some(&msg::source()) {
+            check_tmg_owner(from).await;
+            assert!(
+                !self.approvals.contains_key(&(*from, attribute_id)),
+                "The attribute is approved to another account"
+            );
+        }
+        assert!(
+            self.holds(from, attribute_id),
+            "The sender doesn't own that attribute"
+        );
+
+        self.approvals.remove(&(*from, attribute_id));
+        if let Some(attributes) = self.owners.get_mut(from) {
+            attributes.remove(&attribute_id);
+            if attributes.is_empty() {
+                self.owners.remove(from);
+            }
+        }
+        self.take_off(from, attribute_id);
+        self.owners.entry(*to).or_default().insert(attribute_id);
+
+        msg::reply(
+            StoreEvent::AttributeTransferred {
+                from: *from,
+                to: *to,
+                attribute_id,
+            },
+            0,
+        )
+        .expect("Error in sending a reply `StoreEvent::AttributeTransferred`");
+    }
+
+    fn release_attribute(&mut self, holder: &ActorId, attribute_id: AttributeId) {
+        // the approval may have been cancelled by the previous attempt
+        if let Some(spender) = self.approvals.remove(&(*holder, attribute_id)) {
+            assert_eq!(
+                spender,
+                msg::source(),
+                "Only the approved account can release the attribute"
+            );
+        }
+        msg::reply(
+            StoreEvent::AttributeReleased {
+                holder: *holder,
+                attribute_id,
+            },
+            0,
+        )
+        .expect("Error in sending a reply `StoreEvent::AttributeReleased`");
+    }
+
+    fn get_approval(&self, holder: &ActorId, attribute_id: AttributeId) {
+        let spender = self.approvals.get(&(*holder, attribute_id)).copied();
+        msg::reply(StoreEvent::Approval { spender }, 0)
+            .expect("Error in sending a reply `StoreEvent::Approval`");
+    }
+
+    // Unequips the attribute if the holder wears it
+    fn take_off(&mut self, holder: &ActorId, attribute_id: AttributeId) {
+        if let Some(equipped) = self.equipped.get_mut(holder) {
+            equipped.retain(|_, equipped_id| *equipped_id != attribute_id);
+            if equipped.is_empty() {
+                self.equipped.remove(holder);
+            }
+        }
+    }
+
+    fn holds(&self, holder: &ActorId, attribute_id: AttributeId) -> bool {
+        self.owners
+            .get(holder)
+            .map_or(false, |attributes| attributes.contains(&attribute_id))
+    }
 }
 
 #[gstd::async_main]
@@ -157,6 +347,38 @@ async fn main() {
             store.set_ft_contract_id(&ft_contract_id)
         }
         StoreAction::RemoveTx { tamagotchi_id } => store.remove_tx(&tamagotchi_id),
+        StoreAction::Equip {
+            tamagotchi_id,
+            attribute_id,
+        } => store.equip(&tamagotchi_id, attribute_id).await,
+        StoreAction::Unequip {
+            tamagotchi_id,
+            slot,
+        } => store.unequip(&tamagotchi_id, slot).await,
+        StoreAction::GetEquipped { tamagotchi_id } => store.get_equipped(&tamagotchi_id),
+        StoreAction::GetEffects { tamagotchi_id } => store.get_effects(&tamagotchi_id),
+        StoreAction::ApproveAttribute {
+            holder,
+            attribute_id,
+            spender,
+        } => {
+            store
+                .approve_attribute(&holder, attribute_id, &spender)
+                .await
+        }
+        StoreAction::TransferAttribute {
+            from,
+            to,
+            attribute_id,
+        } => store.transfer_attribute(&from, &to, attribute_id).await,
+        StoreAction::ReleaseAttribute {
+            holder,
+            attribute_id,
+        } => store.release_attribute(&holder, attribute_id),
+        StoreAction::GetApproval {
+            holder,
+            attribute_id,
+        } => store.get_approval(&holder, attribute_id),
     }
 }
 
@@ -200,8 +422,39 @@ async fn transfer_tokens(
     }
 }
 
+// Checks that the message comes either from the Tamagotchi itself or from its owner
+async fn check_tmg_owner(tmg_id: &TamagotchiId) {
+    if msg::source() == *tmg_id {
+        return;
+    }
+    let reply: TmgEvent = msg::send_for_reply_as(*tmg_id, TmgAction::Owner, 0, 0)
+        .expect("Error in sending a message `TmgAction::Owner`")
+        .await
+        .expect("Unable to decode `TmgEvent`");
+    if let TmgEvent::Owner(owner) = reply {
+        assert_eq!(owner, msg::source(), "Only Tamagotchi owner can do that");
+    } else {
+        panic!("Wrong received message");
+    }
+}
+
 #[no_mangle]
 extern "C" fn state() {
     let store = unsafe { STORE.as_ref().expect("The contract is not initialized") };
-    msg::reply(store, 0).expect("Failed to share state");
+    msg::reply(store_io::AttributeStore::from(store), 0).expect("Failed to share state");
+}
+
+impl From<&AttributeStore> for store_io::AttributeStore {
+    fn from(store: &AttributeStore) -> Self {
+        Self {
+            admin: store.admin,
+            ft_contract_id: store.ft_contract_id,
+            attributes: store.attributes.clone(),
+            owners: store.owners.clone(),
+            transaction_id: store.transaction_id,
+            transactions: store.transactions.clone(),
+            equipped: store.equipped.clone(),
+            approvals: store.approvals.clone(),
+        }
+    }
 }
@@ -0,0 +1,48 @@
+#![no_std]
+
+use gmeta::metawasm;
+use gstd::{prelude::*, ActorId};
+use store_io::*;
+
+#[metawasm]
+pub mod metafns {
+    pub type State = AttributeStore;
+
+    pub fn attributes(state: State, tmg_id: TamagotchiId) -> BTreeSet<AttributeId> {
+        state.attributes_of(&tmg_id)
+    }
+
+    pub fn equipped(state: State, tmg_id: TamagotchiId) -> BTreeMap<EquipmentSlot, AttributeId> {
+        state.equipped_of(&tmg_id)
+    }
+
+    pub fn effects(state: State, tmg_id: TamagotchiId) -> Effects {
+        state.effects_of(&tmg_id)
+    }
+
+    pub fn catalog(state: State) -> Vec<(AttributeId, AttrMetadata, Price)> {
+        state.catalog()
+    }
+
+    pub fn attribute_info(
+        state: State,
+        attribute_id: AttributeId,
+    ) -> Option<(AttrMetadata, Price)> {
+        state.attribute(attribute_id)
+    }
+
+    pub fn pending_transaction(
+        state: State,
+        tmg_id: TamagotchiId,
+    ) -> Option<(TransactionId, AttributeId)> {
+        state.pending_transaction(&tmg_id)
+    }
+
+    pub fn approval(state: State, holder: ActorId, attribute_id: AttributeId) -> Option<ActorId> {
+        state.approval_of(&holder, attribute_id)
+    }
+
+    pub fn pending_transactions(state: State) -> Vec<(TamagotchiId, TransactionId, AttributeId)> {
+        state.pending_transactions()
+    }
+}
@@ -0,0 +1,268 @@
+use store_io::*;
+use utils::*;
+
+mod utils;
+
+#[test]
+fn buy_attribute() {
+    let sys = init_system();
+
+    let res = buy(&sys, HOLDER, SWORD);
+    assert!(replied(
+        &res,
+        HOLDER,
+        StoreEvent::AttributeSold { success: true }
+    ));
+    assert!(attributes_of(&sys, HOLDER).contains(&SWORD));
+    assert_eq!(balance_of(&sys, HOLDER), BALANCE - PRICE);
+    assert_eq!(balance_of(&sys, STORE_ID), PRICE);
+    assert!(store_state(&sys).pending_transactions().is_empty());
+
+    assert!(!buy(&sys, HOLDER, HAT).main_failed());
+    // the purchase fails since the holder has run out of tokens
+    let res = buy(&sys, HOLDER, AXE);
+    assert!(replied(
+        &res,
+        HOLDER,
+        StoreEvent::AttributeSold { success: false }
+    ));
+    assert!(!attributes_of(&sys, HOLDER).contains(&AXE));
+}
+
+#[test]
+fn equip_attribute() {
+    let sys = init_system();
+    give_to_tamagotchi(&sys, SWORD);
+    give_to_tamagotchi(&sys, AXE);
+
+    // must fail since only the Tamagotchi and its owner can equip it
+    assert!(equip(&sys, STRANGER, TMG_ID, SWORD).main_failed());
+    // must fail since the Tamagotchi doesn't own that attribute
+    assert!(equip(&sys, TMG_OWNER, TMG_ID, HAT).main_failed());
+
+    let res = equip(&sys, TMG_OWNER, TMG_ID, SWORD);
+    assert!(replied(
+        &res,
+        TMG_OWNER,
+        StoreEvent::AttributeEquipped {
+            tamagotchi_id: TMG_ID.into(),
+            slot: EquipmentSlot::Weapon,
+            attribute_id: SWORD,
+        }
+    ));
+
+    // the attribute replaces the one equipped in the same slot
+    assert!(!equip(&sys, TMG_OWNER, TMG_ID, AXE).main_failed());
+    assert_eq!(
+        store_state(&sys).equipped_of(&TMG_ID.into()),
+        [(EquipmentSlot::Weapon, AXE)].into()
+    );
+}
+
+#[test]
+fn attribute_without_slot() {
+    let sys = init_system();
+    assert!(!buy(&sys, HOLDER, AMULET).main_failed());
+
+    // must fail since the amulet can't be equipped
+    assert!(equip(&sys, HOLDER, HOLDER, AMULET).main_failed());
+    assert!(store_state(&sys).equipped_of(&HOLDER.into()).is_empty());
+}
+
+#[test]
+fn unequip_attribute() {
+    let sys = init_system();
+    give_to_tamagotchi(&sys, SWORD);
+    give_to_tamagotchi(&sys, HAT);
+    assert!(!equip(&sys, TMG_OWNER, TMG_ID, SWORD).main_failed());
+    assert!(!equip(&sys, TMG_OWNER, TMG_ID, HAT).main_failed());
+
+    assert!(unequip(&sys, STRANGER, TMG_ID, EquipmentSlot::Weapon).main_failed());
+
+    let res = unequip(&sys, TMG_OWNER, TMG_ID, EquipmentSlot::Weapon);
+    assert!(replied(
+        &res,
+        TMG_OWNER,
+        StoreEvent::AttributeUnequipped {
+            tamagotchi_id: TMG_ID.into(),
+            slot: EquipmentSlot::Weapon,
+        }
+    ));
+    assert_eq!(
+        store_state(&sys).equipped_of(&TMG_ID.into()),
+        [(EquipmentSlot::Hat, HAT)].into()
+    );
+    // the unequipped attribute is still owned
+    assert!(attributes_of(&sys, TMG_ID).contains(&SWORD));
+
+    // unequipping an empty slot changes nothing
+    assert!(!unequip(&sys, TMG_OWNER, TMG_ID, EquipmentSlot::Armour).main_failed());
+}
+
+#[test]
+fn equipped_effects() {
+    let sys = init_system();
+    give_to_tamagotchi(&sys, AXE);
+    give_to_tamagotchi(&sys, HAT);
+    assert_eq!(
+        store_state(&sys).effects_of(&TMG_ID.into()),
+        Effects::default()
+    );
+
+    // only the equipped attributes have effects
+    assert!(!equip(&sys, TMG_OWNER, TMG_ID, AXE).main_failed());
+    assert!(!equip(&sys, TMG_OWNER, TMG_ID, HAT).main_failed());
+    let effects = Effects {
+        attack_multiplier: 200,
+        defense: 15,
+        energy_bonus: 0,
+        heal: 3,
+    };
+    assert_eq!(store_state(&sys).effects_of(&TMG_ID.into()), effects);
+    let res = sys.get_program(STORE_ID).send(
+        STRANGER,
+        StoreAction::GetEffects {
+            tamagotchi_id: TMG_ID.into(),
+        },
+    );
+    assert!(replied(&res, STRANGER, StoreEvent::Effects { effects }));
+
+    assert!(!unequip(&sys, TMG_OWNER, TMG_ID, EquipmentSlot::Weapon).main_failed());
+    assert_eq!(
+        store_state(&sys).effects_of(&TMG_ID.into()),
+        Effects {
+            defense: 10,
+            heal: 3,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn transfer_attribute() {
+    let sys = init_system();
+    give_to_tamagotchi(&sys, SWORD);
+    assert!(!equip(&sys, TMG_OWNER, TMG_ID, SWORD).main_failed());
+
+    // must fail since only the Tamagotchi and its owner can transfer its attributes
+    assert!(transfer(&sys, STRANGER, TMG_ID, STRANGER, SWORD).main_failed());
+
+    let res = transfer(&sys, TMG_OWNER, TMG_ID, BUYER, SWORD);
+    assert!(replied(
+        &res,
+        TMG_OWNER,
+        StoreEvent::AttributeTransferred {
+            from: TMG_ID.into(),
+            to: BUYER.into(),
+            attribute_id: SWORD,
+        }
+    ));
+    assert!(attributes_of(&sys, TMG_ID).is_empty());
+    assert!(attributes_of(&sys, BUYER).contains(&SWORD));
+    // the transferred attribute is taken off
+    assert!(store_state(&sys).equipped_of(&TMG_ID.into()).is_empty());
+
+    // the repeated transfer only gets the reply
+    assert!(!transfer(&sys, TMG_OWNER, TMG_ID, BUYER, SWORD).main_failed());
+    assert!(attributes_of(&sys, BUYER).contains(&SWORD));
+}
+
+#[test]
+fn transfer_to_holder_of_same_attribute() {
+    let sys = init_system();
+    assert!(!buy(&sys, HOLDER, SWORD).main_failed());
+    assert!(!buy(&sys, BUYER, SWORD).main_failed());
+
+    // the recipient keeps the single attribute
+    assert!(!transfer(&sys, HOLDER, HOLDER, BUYER, SWORD).main_failed());
+    assert!(attributes_of(&sys, HOLDER).is_empty());
+    assert_eq!(attributes_of(&sys, BUYER), [SWORD].into());
+}
+
+#[test]
+fn approve_attribute() {
+    let sys = init_system();
+    give_to_tamagotchi(&sys, SWORD);
+    assert!(!equip(&sys, TMG_OWNER, TMG_ID, SWORD).main_failed());
+
+    // must fail since only the Tamagotchi and its owner can approve its attributes
+    assert!(approve(&sys, STRANGER, TMG_ID, SWORD).main_failed());
+    // must fail since the Tamagotchi doesn't own that attribute
+    assert!(approve(&sys, TMG_OWNER, TMG_ID, HAT).main_failed());
+
+    let res = approve(&sys, TMG_OWNER, TMG_ID, SWORD);
+    assert!(replied(
+        &res,
+        TMG_OWNER,
+        StoreEvent::AttributeApproved {
+            holder: TMG_ID.into(),
+            attribute_id: SWORD,
+            spender: SPENDER.into(),
+        }
+    ));
+    assert_eq!(
+        store_state(&sys).approval_of(&TMG_ID.into(), SWORD),
+        Some(SPENDER.into())
+    );
+    let res = sys.get_program(STORE_ID).send(
+        STRANGER,
+        StoreAction::GetApproval {
+            holder: TMG_ID.into(),
+            attribute_id: SWORD,
+        },
+    );
+    assert!(replied(
+        &res,
+        STRANGER,
+        StoreEvent::Approval {
+            spender: Some(SPENDER.into())
+        }
+    ));
+
+    // the approved attribute is taken off and locked
+    assert!(store_state(&sys).equipped_of(&TMG_ID.into()).is_empty());
+    assert!(equip(&sys, TMG_OWNER, TMG_ID, SWORD).main_failed());
+    assert!(approve(&sys, TMG_OWNER, TMG_ID, SWORD).main_failed());
+    assert!(transfer(&sys, TMG_OWNER, TMG_ID, TMG_OWNER, SWORD).main_failed());
+
+    // the spender transfers the attribute and the approval is used up
+    let res = transfer(&sys, SPENDER, TMG_ID, BUYER, SWORD);
+    assert!(replied(
+        &res,
+        SPENDER,
+        StoreEvent::AttributeTransferred {
+            from: TMG_ID.into(),
+            to: BUYER.into(),
+            attribute_id: SWORD,
+        }
+    ));
+    assert!(attributes_of(&sys, BUYER).contains(&SWORD));
+    assert_eq!(store_state(&sys).approval_of(&TMG_ID.into(), SWORD), None);
+}
+
+#[test]
+fn release_attribute() {
+    let sys = init_system();
+    assert!(!buy(&sys, HOLDER, SWORD).main_failed());
+    assert!(!approve(&sys, HOLDER, HOLDER, SWORD).main_failed());
+
+    // must fail since only the approved account can release the attribute
+    assert!(release(&sys, HOLDER, HOLDER, SWORD).main_failed());
+
+    let res = release(&sys, SPENDER, HOLDER, SWORD);
+    assert!(replied(
+        &res,
+        SPENDER,
+        StoreEvent::AttributeReleased {
+            holder: HOLDER.into(),
+            attribute_id: SWORD,
+        }
+    ));
+    assert_eq!(store_state(&sys).approval_of(&HOLDER.into(), SWORD), None);
+    assert!(attributes_of(&sys, HOLDER).contains(&SWORD));
+
+    // the repeated release only gets the reply
+    assert!(!release(&sys, SPENDER, HOLDER, SWORD).main_failed());
+    // the released attribute can be used again
+    assert!(!equip(&sys, HOLDER, HOLDER, SWORD).main_failed());
+}
@@ -0,0 +1,283 @@
+#![allow(dead_code)]
+
+use ft_main_io::{FTokenAction, FTokenEvent, LogicAction};
+use gstd::ActorId;
+use gtest::{Log, Program, RunResult, System, WasmProgram};
+use parity_scale_codec::{Decode, Encode};
+use std::collections::{BTreeMap, BTreeSet};
+use store_io::*;
+use tmg_io::{TmgAction, TmgEvent};
+
+pub const STORE_ID: u64 = 1;
+pub const FT_ID: u64 = 2;
+pub const TMG_ID: u64 = 3;
+pub const ADMIN: u64 = 98;
+pub const TMG_OWNER: u64 = 100;
+// accounts can hold attributes by themselves like Tamagotchis do
+pub const HOLDER: u64 = 101;
+pub const BUYER: u64 = 102;
+pub const SPENDER: u64 = 103;
+pub const STRANGER: u64 = 200;
+pub const PRICE: Price = 1_000;
+pub const BALANCE: u128 = 2 * PRICE;
+
+pub const SWORD: AttributeId = 1;
+pub const AXE: AttributeId = 2;
+pub const HAT: AttributeId = 3;
+pub const AMULET: AttributeId = 4;
+
+/// Tamagotchi that only knows its owner.
+#[derive(Debug)]
+pub struct TamagotchiMock {
+    owner: ActorId,
+}
+
+impl WasmProgram for TamagotchiMock {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        match TmgAction::decode(&mut &payload[..]) {
+            Ok(TmgAction::Owner) => Ok(Some(TmgEvent::Owner(self.owner).encode())),
+            _ => Err("Unexpected `TmgAction`"),
+        }
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Ok(self.owner.encode())
+    }
+}
+
+/// Token ledger that only moves tokens between accounts.
+#[derive(Debug, Default)]
+pub struct TokenMock {
+    balances: BTreeMap<ActorId, u128>,
+}
+
+impl WasmProgram for TokenMock {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let (sender, recipient, amount) = match FTokenAction::decode(&mut &payload[..]) {
+            Ok(FTokenAction::Message {
+                payload:
+                    LogicAction::Transfer {
+                        sender,
+                        recipient,
+                        amount,
+                    },
+                ..
+            }) => (sender, recipient, amount),
+            _ => return Err("Unexpected `FTokenAction`"),
+        };
+        let balance = self.balances.get(&sender).copied().unwrap_or_default();
+        if balance < amount {
+            return Ok(Some(FTokenEvent::Err.encode()));
+        }
+        self.balances.insert(sender, balance - amount);
+        *self.balances.entry(recipient).or_default() += amount;
+        Ok(Some(FTokenEvent::Ok.encode()))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Ok(self.balances.encode())
+    }
+}
+
+pub fn metadata(slot: Option<EquipmentSlot>, effects: Vec<AttrEffect>) -> AttrMetadata {
+    AttrMetadata {
+        title: String::from("Attribute"),
+        description: String::from("Attribute of the test"),
+        media: String::from("https://example.com/attribute.png"),
+        slot,
+        effects,
+    }
+}
+
+/// Deploys the store with the catalog of the test attributes and the mocks it talks to.
+/// `HOLDER` and `BUYER` can pay for two attributes each, the Tamagotchi belongs to `TMG_OWNER`.
+pub fn init_system() -> System {
+    let sys = System::new();
+    sys.init_logger();
+
+    let store = Program::current(&sys);
+    let res = store.send(ADMIN, ActorId::from(FT_ID));
+    assert!(!res.main_failed());
+
+    let catalog = [
+        (
+            SWORD,
+            metadata(
+                Some(EquipmentSlot::Weapon),
+                vec![AttrEffect::AttackMultiplier(150)],
+            ),
+        ),
+        (
+            AXE,
+            metadata(
+                Some(EquipmentSlot::Weapon),
+                vec![AttrEffect::AttackMultiplier(200), AttrEffect::Defense(5)],
+            ),
+        ),
+        (
+            HAT,
+            metadata(
+                Some(EquipmentSlot::Hat),
+                vec![AttrEffect::Defense(10), AttrEffect::Heal(3)],
+            ),
+        ),
+        (AMULET, metadata(None, vec![AttrEffect::EnergyBonus(20)])),
+    ];
+    for (attribute_id, attr_metadata) in catalog {
+        let res = store.send(
+            ADMIN,
+            StoreAction::CreateAttribute {
+                attribute_id,
+                attr_metadata,
+                price: PRICE,
+            },
+        );
+        assert!(replied(
+            &res,
+            ADMIN,
+            StoreEvent::AttributeCreated { attribute_id }
+        ));
+    }
+
+    Program::mock_with_id(
+        &sys,
+        FT_ID,
+        TokenMock {
+            balances: [HOLDER, BUYER]
+                .into_iter()
+                .map(|account| (account.into(), BALANCE))
+                .collect(),
+        },
+    );
+    Program::mock_with_id(
+        &sys,
+        TMG_ID,
+        TamagotchiMock {
+            owner: TMG_OWNER.into(),
+        },
+    );
+
+    sys
+}
+
+pub fn buy(sys: &System, account: u64, attribute_id: AttributeId) -> RunResult {
+    sys.get_program(STORE_ID)
+        .send(account, StoreAction::BuyAttribute { attribute_id })
+}
+
+/// Buys the attribute and moves it to the Tamagotchi.
+pub fn give_to_tamagotchi(sys: &System, attribute_id: AttributeId) {
+    let res = buy(sys, HOLDER, attribute_id);
+    assert!(replied(
+        &res,
+        HOLDER,
+        StoreEvent::AttributeSold { success: true }
+    ));
+    let res = transfer(sys, HOLDER, HOLDER, TMG_ID, attribute_id);
+    assert!(!res.main_failed());
+}
+
+pub fn equip(sys: &System, account: u64, tmg_id: u64, attribute_id: AttributeId) -> RunResult {
+    sys.get_program(STORE_ID).send(
+        account,
+        StoreAction::Equip {
+            tamagotchi_id: tmg_id.into(),
+            attribute_id,
+        },
+    )
+}
+
+pub fn unequip(sys: &System, account: u64, tmg_id: u64, slot: EquipmentSlot) -> RunResult {
+    sys.get_program(STORE_ID).send(
+        account,
+        StoreAction::Unequip {
+            tamagotchi_id: tmg_id.into(),
+            slot,
+        },
+    )
+}
+
+pub fn approve(sys: &System, account: u64, holder: u64, attribute_id: AttributeId) -> RunResult {
+    sys.get_program(STORE_ID).send(
+        account,
+        StoreAction::ApproveAttribute {
+            holder: holder.into(),
+            attribute_id,
+            spender: SPENDER.into(),
+        },
+    )
+}
+
+pub fn transfer(
+    sys: &System,
+    account: u64,
+    from: u64,
+    to: u64,
+    attribute_id: AttributeId,
+) -> RunResult {
+    sys.get_program(STORE_ID).send(
+        account,
+        StoreAction::TransferAttribute {
+            from: from.into(),
+            to: to.into(),
+            attribute_id,
+        },
+    )
+}
+
+pub fn release(sys: &System, account: u64, holder: u64, attribute_id: AttributeId) -> RunResult {
+    sys.get_program(STORE_ID).send(
+        account,
+        StoreAction::ReleaseAttribute {
+            holder: holder.into(),
+            attribute_id,
+        },
+    )
+}
+
+pub fn store_state(sys: &System) -> AttributeStore {
+    sys.get_program(STORE_ID)
+        .read_state()
+        .expect("Unable to read the store state")
+}
+
+pub fn attributes_of(sys: &System, account: u64) -> BTreeSet<AttributeId> {
+    store_state(sys).attributes_of(&account.into())
+}
+
+pub fn balance_of(sys: &System, account: u64) -> u128 {
+    let balances: BTreeMap<ActorId, u128> = sys
+        .get_program(FT_ID)
+        .read_state()
+        .expect("Unable to read the token state");
+    balances.get(&account.into()).copied().unwrap_or_default()
+}
+
+/// Checks that the store replied to the account with the given event.
+pub fn replied(res: &RunResult, account: u64, event: StoreEvent) -> bool {
+    res.contains(&Log::builder().dest(account).payload(event))
+}
@@ -1,3 +1,5 @@
+use auction_io::AuctionMetadata;
+
 fn main() {
-    gear_wasm_builder::build();
+    gear_wasm_builder::build_with_metadata::<AuctionMetadata>();
 }
@@ -1,36 +1,403 @@
 #![no_std]
 
+use blake2_rfc::blake2b::blake2b;
+use gmeta::{In, InOut, Metadata};
 use gstd::{prelude::*, ActorId};
+pub use nft_io::TokenId;
+pub use store_io::AttributeId;
 
 pub type TamagotchiId = ActorId;
 pub type TransactionId = u64;
 pub type Bid = u128;
-pub type Duration = u64;
+/// Durations and deadlines are measured in blocks.
+pub type Duration = u32;
+pub type BlockNumber = u32;
+pub type BidHash = [u8; 32];
 
-#[derive(Encode, Decode)]
+/// An item that can be sold on the auction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Encode, Decode, TypeInfo)]
+pub enum Asset {
+    Tamagotchi(TamagotchiId),
+    /// A token of a program implementing the standard gear non-fungible token.
+    Nft {
+        contract_id: ActorId,
+        token_id: TokenId,
+    },
+    /// An attribute of the Tamagotchi store held by `holder`.
+    /// Only the holder can sell it (it can be moved to the seller's account
+    /// from their Tamagotchi with `StoreAction::TransferAttribute`).
+    /// Before the start the holder approves the auction with `StoreAction::ApproveAttribute`,
+    /// so the attribute stays with the holder locked by the approval until the settlement.
+    Attribute {
+        store_id: ActorId,
+        attribute_id: AttributeId,
+        holder: ActorId,
+    },
+}
+
+impl Asset {
+    /// Returns the program that keeps the ownership of the asset.
+    pub fn collection(&self) -> ActorId {
+        match self {
+            Self::Tamagotchi(tamagotchi_id) => *tamagotchi_id,
+            Self::Nft { contract_id, .. } => *contract_id,
+            Self::Attribute { store_id, .. } => *store_id,
+        }
+    }
+}
+
+/// The number of settled auctions kept in the history of each asset.
+pub const MAX_HISTORY_LEN: usize = 32;
+
+pub struct AuctionMetadata;
+
+impl Metadata for AuctionMetadata {
+    type Init = In<InitAuction>;
+    type Handle = InOut<AuctionAction, Result<AuctionEvent, AuctionError>>;
+    type Reply = ();
+    type Others = ();
+    type Signal = ();
+    type State = AuctionState;
+}
+
+#[derive(Default, Encode, Decode, TypeInfo)]
+pub struct InitAuction {
+    pub ft_contract_id: ActorId,
+    /// The account receiving the protocol fee.
+    pub fee_collector: ActorId,
+    /// The percent of the sale price taken as the protocol fee on settlement.
+    pub fee_percent: u8,
+}
+
+#[derive(Default, Encode, Decode, TypeInfo)]
+pub struct AuctionState {
+    pub admin: ActorId,
+    pub ft_contract_id: ActorId,
+    pub fee_collector: ActorId,
+    pub fee_percent: u8,
+    pub transaction_id: TransactionId,
+    pub auctions: Vec<(Asset, AuctionInfo)>,
+    /// Programs whose assets can be put up for auction.
+    pub allowlist: Vec<ActorId>,
+    /// Gas reservations available for the signal handler.
+    pub reservations: Vec<ReservationInfo>,
+    /// Tokens that can be claimed back by outbid bidders.
+    pub refunds: Vec<(ActorId, Bid)>,
+    /// Refund claims whose transfer hasn't been completed yet.
+    pub refund_claims: Vec<(ActorId, (TransactionId, Bid))>,
+}
+
+impl AuctionState {
+    pub fn auction(&self, asset: &Asset) -> Option<&AuctionInfo> {
+        self.auctions
+            .iter()
+            .find(|(id, _)| id == asset)
+            .map(|(_, auction)| auction)
+    }
+
+    pub fn is_allowed(&self, asset: &Asset) -> bool {
+        self.allowlist.contains(&asset.collection())
+    }
+
+    /// Returns the tokens the account can claim including the pending claim.
+    pub fn refund_of(&self, account: &ActorId) -> Bid {
+        let refund = self
+            .refunds
+            .iter()
+            .find(|(id, _)| id == account)
+            .map(|(_, refund)| *refund)
+            .unwrap_or_default();
+        let claim = self
+            .refund_claims
+            .iter()
+            .find(|(id, _)| id == account)
+            .map(|(_, (_, amount))| *amount)
+            .unwrap_or_default();
+        refund + claim
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct ReservationInfo {
+    pub amount: u64,
+    pub created_at: BlockNumber,
+    /// The reservation can't be used since this block.
+    pub expires_at: BlockNumber,
+}
+
+#[derive(Default, Clone, Encode, Decode, TypeInfo)]
+pub struct AuctionInfo {
+    pub kind: AuctionKind,
+    pub status: Status,
+    pub current_bid: Bid,
+    pub current_bidder: ActorId,
+    pub started_at: BlockNumber,
+    /// The block at which the bidding ends.
+    pub ended_at: BlockNumber,
+    pub seller: ActorId,
+    /// The lowest winning bid for which the asset is sold.
+    /// Otherwise the asset is returned to the seller on settlement.
+    pub reserve_price: Option<Bid>,
+    pub transaction: Option<Transaction>,
+    /// Bids made in the current auction in the order they were accepted.
+    pub bids: Vec<BidRecord>,
+    /// Unrevealed bids of the sealed-bid auction.
+    pub commitments: BTreeMap<ActorId, Commitment>,
+    /// Settled auctions of the asset from the oldest one.
+    /// Only the last `MAX_HISTORY_LEN` auctions are kept.
+    pub history: Vec<CompletedAuction>,
+}
+
+impl AuctionInfo {
+    /// Returns the block after which the auction can be settled.
+    pub fn settlement_block(&self) -> BlockNumber {
+        match self.kind {
+            AuctionKind::SealedBid {
+                reveal_duration, ..
+            } => self.ended_at.saturating_add(reveal_duration),
+            _ => self.ended_at,
+        }
+    }
+
+    /// Returns whether the current bid wins the asset.
+    pub fn reserve_met(&self) -> bool {
+        !self.current_bidder.is_zero()
+            && self
+                .reserve_price
+                .map_or(true, |reserve_price| self.current_bid >= reserve_price)
+    }
+
+    /// Returns the smallest bid the English auction accepts now.
+    pub fn min_next_bid(&self) -> Option<Bid> {
+        if let AuctionKind::English { bid_increment, .. } = self.kind {
+            // the first bid may be the minimum one
+            if self.current_bidder.is_zero() {
+                return Some(self.current_bid);
+            }
+            let increment = match bid_increment {
+                BidIncrement::Absolute(amount) => amount,
+                BidIncrement::Percent(percent) => self.current_bid * percent as u128 / 100,
+            };
+            Some(self.current_bid.saturating_add(increment.max(1)))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the price of the Dutch auction at the given block.
+    pub fn dutch_price(&self, block: BlockNumber) -> Option<Bid> {
+        if let AuctionKind::Dutch {
+            starting_price,
+            price_step,
+            floor_price,
+        } = self.kind
+        {
+            let blocks = block.saturating_sub(self.started_at) as u128;
+            let price = starting_price.saturating_sub(price_step.saturating_mul(blocks));
+            Some(price.max(floor_price))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct BidRecord {
+    pub bidder: ActorId,
+    pub amount: Bid,
+    pub block: BlockNumber,
+    /// The block timestamp in milliseconds.
+    pub timestamp: u64,
+    /// The transaction that transferred the bid to the auction.
+    /// Revealed sealed bids are paid by their deposits so they don't have one.
+    pub transaction_id: Option<TransactionId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct CompletedAuction {
+    pub kind: AuctionKind,
+    pub seller: ActorId,
+    /// `None` if nobody has bid or the reserve price isn't met.
+    pub winner: Option<ActorId>,
+    /// The price paid by the winner (`0` if there is no winner).
+    pub final_price: Bid,
+    pub started_at: BlockNumber,
+    pub settled_at: BlockNumber,
+    pub bids: Vec<BidRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub enum AuctionKind {
+    /// Ascending bids starting from the minimum one.
+    /// Each next bid must exceed the previous one at least by `bid_increment`.
+    /// The auction ends immediately once someone bids at least `buy_now_price`.
+    English {
+        minimum_bid: Bid,
+        buy_now_price: Option<Bid>,
+        bid_increment: BidIncrement,
+        anti_sniping: Option<AntiSniping>,
+    },
+    /// The price decreases by `price_step` every block (but not below `floor_price`)
+    /// until the first buyer accepts it.
+    Dutch {
+        starting_price: Bid,
+        price_step: Bid,
+        floor_price: Bid,
+    },
+    /// Bidders commit hashes of their bids together with deposits covering them
+    /// and reveal the bids during `reveal_duration` blocks after the end of the bidding.
+    /// Deposits of unrevealed bids are given to the seller,
+    /// the rest of deposits are credited to the refunds of bidders.
+    SealedBid {
+        minimum_bid: Bid,
+        reveal_duration: Duration,
+    },
+}
+
+impl Default for AuctionKind {
+    fn default() -> Self {
+        Self::English {
+            minimum_bid: 0,
+            buy_now_price: None,
+            bid_increment: BidIncrement::Absolute(1),
+            anti_sniping: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub enum BidIncrement {
+    Absolute(Bid),
+    /// Percent of the current bid.
+    Percent(u8),
+}
+
+/// A bid made less than `window` blocks before the end of the auction
+/// extends the auction so that it ends `extension` blocks after the bid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct AntiSniping {
+    pub window: Duration,
+    pub extension: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Commitment {
+    pub hash: BidHash,
+    pub deposit: Bid,
+}
+
+/// Returns the hash that is committed in the sealed-bid auction: `blake2b_256(bid ++ salt)`.
+pub fn bid_hash(bid: Bid, salt: &[u8; 32]) -> BidHash {
+    let mut data = bid.encode();
+    data.extend_from_slice(salt);
+    let mut hash = [0; 32];
+    hash.copy_from_slice(blake2b(32, &[], &data).as_bytes());
+    hash
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Encode, Decode, TypeInfo)]
+pub enum Status {
+    #[default]
+    ReadyToStart,
+    InProcess,
+}
+
+#[derive(Encode, Decode, TypeInfo)]
 pub enum AuctionAction {
+    /// The seller has to approve the asset to the auction beforehand.
     StartAuction {
-        tamagotchi_id: TamagotchiId,
-        minimum_bid: Bid,
-        duration: u64,
+        asset: Asset,
+        duration: Duration,
+        kind: AuctionKind,
+        /// Not allowed for the Dutch auction which has the floor price instead.
+        reserve_price: Option<Bid>,
+    },
+    /// Returns the asset to the seller.
+    /// Possible only until the first bid is made.
+    CancelAuction {
+        asset: Asset,
     },
+    /// Makes a bid in the English auction or buys the asset in the Dutch one
+    /// (`bid` is the highest price the buyer agrees to pay).
     MakeBid {
+        asset: Asset,
+        bid: Bid,
+    },
+    /// Commits a bid in the sealed-bid auction.
+    CommitBid {
+        asset: Asset,
+        hash: BidHash,
+        deposit: Bid,
+    },
+    RevealBid {
+        asset: Asset,
         bid: Bid,
+        salt: [u8; 32],
     },
-    SettleAuction,
+    SettleAuction {
+        asset: Asset,
+    },
+    /// Transfers the tokens of the outbid (or not winning) bids back to the bidder.
+    ClaimRefund,
+    /// Reserves gas for the signal handler in addition to the reservations
+    /// made automatically while handling other actions.
     MakeReservation,
     CompleteTx(Transaction),
+    /// Allows the assets of the programs (Tamagotchis or NFT contracts)
+    /// to be put up for auction (admin only).
+    ///
+    /// Programs are allowed by their ids:
+    /// a program can't learn the code id of another program on-chain,
+    /// so the admin has to check the code of each program before allowing it.
+    AllowCollections(Vec<ActorId>),
+    /// Forbids new auctions for the assets of the programs (admin only).
+    /// The auctions already in process aren't affected.
+    DisallowCollections(Vec<ActorId>),
+    SetAdmin(ActorId),
 }
 
-#[derive(Encode, Decode)]
+#[derive(Encode, Decode, TypeInfo)]
 pub enum AuctionEvent {
-    AuctionStarted,
-    BidMade { bid: Bid },
-    AuctionSettled,
+    AuctionStarted {
+        asset: Asset,
+    },
+    BidMade {
+        asset: Asset,
+        bid: Bid,
+    },
+    AuctionExtended {
+        asset: Asset,
+        bid: Bid,
+        ended_at: BlockNumber,
+    },
+    AuctionSettled {
+        asset: Asset,
+    },
+    /// The auction is settled but the winning bid is lower than the reserve price,
+    /// so the bid is returned and the asset goes back to the seller.
+    ReserveNotMet {
+        asset: Asset,
+        bid: Bid,
+    },
+    AuctionCancelled {
+        asset: Asset,
+    },
+    RefundClaimed {
+        amount: Bid,
+    },
+    AllowlistUpdated,
+    AdminChanged(ActorId),
+    BidCommitted {
+        asset: Asset,
+    },
+    BidRevealed {
+        asset: Asset,
+        bid: Bid,
+    },
     ReservationMade,
 }
 
-#[derive(Encode, Decode)]
+#[derive(Encode, Decode, TypeInfo)]
 pub enum AuctionError {
     RerunTransaction,
     UnableToChangeOwner,
@@ -43,21 +410,99 @@ pub enum AuctionError {
     NoTx,
     WrongReceivedMessage,
     NotOwner,
+    NoAuction,
+    WrongKind,
+    WrongHash,
+    NoRefund,
+    NotAdmin,
+    NotAllowed,
 }
 
-#[derive(Clone, Encode, Decode, PartialEq, Eq)]
+#[derive(Clone, Encode, Decode, PartialEq, Eq, TypeInfo)]
 pub enum Transaction {
     StartAuction {
-        tamagotchi_id: TamagotchiId,
-        bid: Bid,
-        duration: u64,
+        asset: Asset,
+        transaction_id: TransactionId,
+        seller: ActorId,
+        duration: Duration,
+        kind: AuctionKind,
+        reserve_price: Option<Bid>,
+    },
+    CancelAuction {
+        asset: Asset,
+        transaction_id: TransactionId,
     },
     MakeBid {
+        asset: Asset,
         transaction_id: TransactionId,
         bidder: ActorId,
         bid: u128,
     },
+    CommitBid {
+        asset: Asset,
+        transaction_id: TransactionId,
+        bidder: ActorId,
+        hash: BidHash,
+        deposit: Bid,
+    },
     SettleAuction {
+        asset: Asset,
         transaction_id: TransactionId,
     },
 }
+
+/// Returns `limit` items starting from `offset`.
+pub fn page<T: Clone>(items: &[T], offset: u32, limit: u32) -> Vec<T> {
+    items
+        .iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .cloned()
+        .collect()
+}
+
+impl Transaction {
+    /// Returns the asset whose auction the transaction belongs to.
+    pub fn asset(&self) -> Asset {
+        match self {
+            Self::StartAuction { asset, .. }
+            | Self::CancelAuction { asset, .. }
+            | Self::MakeBid { asset, .. }
+            | Self::CommitBid { asset, .. }
+            | Self::SettleAuction { asset, .. } => *asset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_in_range() {
+        let items = [1, 2, 3, 4, 5];
+        assert_eq!(page(&items, 0, 2), vec![1, 2]);
+        assert_eq!(page(&items, 2, 2), vec![3, 4]);
+    }
+
+    #[test]
+    fn last_partial_page() {
+        let items = [1, 2, 3, 4, 5];
+        assert_eq!(page(&items, 4, 2), vec![5]);
+        assert_eq!(page(&items, 3, u32::MAX), vec![4, 5]);
+    }
+
+    #[test]
+    fn offset_past_end() {
+        let items = [1, 2, 3];
+        assert!(page(&items, 3, 2).is_empty());
+        assert!(page(&items, u32::MAX, 2).is_empty());
+    }
+
+    #[test]
+    fn zero_limit() {
+        let items = [1, 2, 3];
+        assert!(page(&items, 0, 0).is_empty());
+        assert!(page::<u8>(&[], 0, 0).is_empty());
+    }
+}
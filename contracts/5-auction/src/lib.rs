@@ -2,66 +2,88 @@
 
 use auction_io::*;
 use ft_main_io::{FTokenAction, FTokenEvent, LogicAction};
-use gstd::{errors::Result, exec, msg, prelude::*, ActorId, ReservationId};
+use gstd::{errors::Result, exec, msg, prelude::*, ActorId, MessageId, ReservationId};
+use nft_io::{NFTAction, NFTEvent};
+use store_io::{StoreAction, StoreEvent};
 use tmg_io::*;
-const MIN_DURATION: u64 = 300_000;
+const MIN_DURATION: Duration = 100;
 const RESERVATION_AMOUNT: u64 = 50_000_000_000;
 const RESERVATION_DURATION: u32 = 86400;
+const RESERVATION_POOL_SIZE: usize = 4;
+// the gas left for the rest of the execution after topping up the reservations
+const RESERVATION_GAS_MARGIN: u64 = 5_000_000_000;
 const SYSTEM_GAS: u64 = 1_000_000_000;
-#[derive(Debug, PartialEq, Eq)]
-pub enum Status {
-    ReadyToStart,
-    InProcess,
-}
-
-impl Default for Status {
-    fn default() -> Self {
-        Self::ReadyToStart
-    }
-}
 
 static mut AUCTION: Option<Auction> = None;
 
 #[derive(Default)]
 pub struct Auction {
-    tamagotchi_id: TamagotchiId,
-    status: Status,
-    current_bid: u128,
-    current_bidder: ActorId,
+    admin: ActorId,
     ft_contract_id: ActorId,
-    transaction: Option<Transaction>,
+    fee_collector: ActorId,
+    fee_percent: u8,
     transaction_id: TransactionId,
-    ended_at: u64,
-    prev_tmg_owner: ActorId,
-    reservations: Vec<ReservationId>,
+    auctions: BTreeMap<Asset, AuctionInfo>,
+    allowlist: BTreeSet<ActorId>,
+    reservations: Vec<Reservation>,
+    refunds: BTreeMap<ActorId, Bid>,
+    refund_claims: BTreeMap<ActorId, (TransactionId, Bid)>,
+    // the assets whose transactions the messages being executed complete,
+    // so only the transaction of the failed message is resent
+    tx_messages: BTreeMap<MessageId, Asset>,
+}
+
+pub struct Reservation {
+    id: ReservationId,
+    created_at: BlockNumber,
+}
+
+impl Reservation {
+    fn expires_at(&self) -> BlockNumber {
+        self.created_at.saturating_add(RESERVATION_DURATION)
+    }
 }
 
 impl Auction {
     async fn start_auction(
         &mut self,
-        tamagotchi_id: &TamagotchiId,
-        minimum_bid: Bid,
-        duration: u64,
+        asset: &Asset,
+        duration: Duration,
+        kind: AuctionKind,
+        reserve_price: Option<Bid>,
     ) -> Result<AuctionEvent, AuctionError> {
-        if self.status != Status::ReadyToStart {
-            return Err(AuctionError::WrongState);
+        if !self.allowlist.contains(&asset.collection()) {
+            return Err(AuctionError::NotAllowed);
         }
+        // the auction of the asset is created only once the start is accepted
+        let pending_tx = match self.auctions.get(asset) {
+            Some(auction) if auction.status != Status::ReadyToStart => {
+                return Err(AuctionError::WrongState)
+            }
+            Some(auction) => auction.transaction.clone(),
+            None => None,
+        };
 
         // Check if there is already a pending transaction
-        if let Some(tx) = self.transaction.clone() {
+        if let Some(tx) = pending_tx {
             match tx {
                 Transaction::StartAuction {
-                    tamagotchi_id: prev_tmg_id,
-                    bid,
+                    seller,
                     duration: prev_duration,
+                    kind: ref prev_kind,
+                    reserve_price: prev_reserve_price,
+                    ..
                 } => {
-                    if *tamagotchi_id != prev_tmg_id
-                        || bid != minimum_bid
+                    if seller != msg::source()
                         || duration != prev_duration
+                        || kind != *prev_kind
+                        || reserve_price != prev_reserve_price
                     {
                         return Err(AuctionError::WrongParams);
                     }
-                    return self.complete_tx(tx).await;
+                    let result = self.complete_tx(tx).await;
+                    self.discard_unstarted_auction(asset);
+                    return result;
                 }
                 _ => {
                     return Err(AuctionError::WrongTx);
@@ -72,145 +94,401 @@ impl Auction {
         if duration < MIN_DURATION {
             return Err(AuctionError::WrongDuration);
         }
+        match kind {
+            AuctionKind::Dutch {
+                starting_price,
+                floor_price,
+                ..
+            } => {
+                if floor_price > starting_price || reserve_price.is_some() {
+                    return Err(AuctionError::WrongParams);
+                }
+            }
+            // buying now must always win the asset
+            AuctionKind::English {
+                buy_now_price: Some(buy_now_price),
+                ..
+            } => {
+                if reserve_price.map_or(false, |reserve_price| reserve_price > buy_now_price) {
+                    return Err(AuctionError::WrongParams);
+                }
+            }
+            _ => {}
+        }
 
+        // the asset can be on the contract only after the interrupted run of the pending start,
+        // so a new start requires the seller to own the asset
+        check_seller(asset, &msg::source()).await?;
+        // another start of the asset may have begun while waiting for the owner
+        if self.auctions.get(asset).map_or(false, |auction| {
+            auction.status != Status::ReadyToStart || auction.transaction.is_some()
+        }) {
+            return Err(AuctionError::WrongState);
+        }
+
+        let transaction_id = self.transaction_id;
+        self.transaction_id = self.transaction_id.wrapping_add(1);
         let tx = Transaction::StartAuction {
-            tamagotchi_id: *tamagotchi_id,
-            bid: minimum_bid,
+            asset: *asset,
+            transaction_id,
+            seller: msg::source(),
             duration,
+            kind,
+            reserve_price,
         };
-        self.transaction = Some(tx.clone());
+        self.auctions.entry(*asset).or_default().transaction = Some(tx.clone());
 
-        self.complete_tx(tx).await
+        let result = self.complete_tx(tx).await;
+        self.discard_unstarted_auction(asset);
+        result
     }
 
-    async fn make_bid(&mut self, bid: u128) -> Result<AuctionEvent, AuctionError> {
-        if self.status != Status::InProcess {
+    // Removes the auction created for the start that has failed
+    // unless it keeps the history of the previous auctions of the asset
+    fn discard_unstarted_auction(&mut self, asset: &Asset) {
+        if let Some(auction) = self.auctions.get(asset) {
+            if auction.status == Status::ReadyToStart
+                && auction.transaction.is_none()
+                && auction.history.is_empty()
+            {
+                self.auctions.remove(asset);
+            }
+        }
+    }
+
+    async fn cancel_auction(&mut self, asset: &Asset) -> Result<AuctionEvent, AuctionError> {
+        let auction = self.get_auction(asset)?;
+        if auction.status != Status::InProcess {
             return Err(AuctionError::WrongState);
         }
+        if auction.seller != msg::source() {
+            return Err(AuctionError::NotOwner);
+        }
 
-        // Check if there is already a pending transaction
-        if let Some(tx) = self.transaction.clone() {
+        // It is possible that there is a pending bid transaction
+        if let Some(tx) = auction.transaction.clone() {
             match tx {
-                Transaction::MakeBid {
-                    transaction_id: _,
-                    bidder,
-                    bid: prev_bid,
-                } => {
-                    let result = self.complete_tx(tx).await;
-                    if bidder == msg::source() && bid == prev_bid {
-                        return result;
-                    }
+                Transaction::CancelAuction { .. } => {
+                    return self.complete_tx(tx).await;
                 }
-                _ => {
+                Transaction::StartAuction { .. } | Transaction::SettleAuction { .. } => {
                     return Err(AuctionError::WrongTx);
                 }
+                _ => {
+                    _ = self.complete_tx(tx).await;
+                }
             }
         }
 
-        if bid <= self.current_bid {
-            return Err(AuctionError::WrongBid);
+        let auction = self.get_auction(asset)?;
+        if !auction.bids.is_empty() || !auction.commitments.is_empty() {
+            return Err(AuctionError::WrongState);
         }
 
         let transaction_id = self.transaction_id;
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        let tx = Transaction::CancelAuction {
+            asset: *asset,
+            transaction_id,
+        };
+        self.get_auction(asset)?.transaction = Some(tx.clone());
+        self.complete_tx(tx).await
+    }
+
+    async fn make_bid(&mut self, asset: &Asset, bid: u128) -> Result<AuctionEvent, AuctionError> {
+        self.check_bidding(asset)?;
+
         let bidder = msg::source();
-        self.transaction_id = self.transaction_id.wrapping_add(2);
+        if let Some(result) = self
+            .complete_pending_bid(asset, |tx| {
+                matches!(
+                    tx,
+                    Transaction::MakeBid { bidder: prev_bidder, bid: prev_bid, .. }
+                        if *prev_bidder == bidder && *prev_bid == bid
+                )
+            })
+            .await
+        {
+            return result;
+        }
+        // the completed bid may have bought the asset
+        self.check_bidding(asset)?;
+
+        let auction = self.get_auction(asset)?;
+        let bid = match auction.kind {
+            AuctionKind::English { .. } => {
+                if bid < auction.min_next_bid().expect("The auction is English") {
+                    return Err(AuctionError::WrongBid);
+                }
+                bid
+            }
+            AuctionKind::Dutch { .. } => {
+                // the asset is already sold
+                if !auction.current_bidder.is_zero() {
+                    return Err(AuctionError::WrongState);
+                }
+                let price = auction
+                    .dutch_price(exec::block_height())
+                    .expect("The auction is Dutch");
+                if bid < price {
+                    return Err(AuctionError::WrongBid);
+                }
+                price
+            }
+            AuctionKind::SealedBid { .. } => return Err(AuctionError::WrongKind),
+        };
+
+        let transaction_id = self.transaction_id;
+        self.transaction_id = self.transaction_id.wrapping_add(1);
         let tx = Transaction::MakeBid {
+            asset: *asset,
             transaction_id,
             bidder,
             bid,
         };
-        self.transaction = Some(tx.clone());
+        self.get_auction(asset)?.transaction = Some(tx.clone());
+        let result = self.complete_tx(tx).await;
+
+        // the auction ends immediately when the asset is bought
+        if result.is_ok() && self.get_auction(asset)?.ended_at <= exec::block_height() {
+            return self.settle_auction(asset).await;
+        }
+        result
+    }
+
+    async fn commit_bid(
+        &mut self,
+        asset: &Asset,
+        hash: BidHash,
+        deposit: Bid,
+    ) -> Result<AuctionEvent, AuctionError> {
+        self.check_bidding(asset)?;
+
+        let bidder = msg::source();
+        if let Some(result) = self
+            .complete_pending_bid(asset, |tx| {
+                matches!(
+                    tx,
+                    Transaction::CommitBid { bidder: prev_bidder, hash: prev_hash, .. }
+                        if *prev_bidder == bidder && *prev_hash == hash
+                )
+            })
+            .await
+        {
+            return result;
+        }
+
+        let auction = self.get_auction(asset)?;
+        if !matches!(auction.kind, AuctionKind::SealedBid { .. }) {
+            return Err(AuctionError::WrongKind);
+        }
+        if deposit == 0 || auction.commitments.contains_key(&bidder) {
+            return Err(AuctionError::WrongBid);
+        }
+
+        let transaction_id = self.transaction_id;
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        let tx = Transaction::CommitBid {
+            asset: *asset,
+            transaction_id,
+            bidder,
+            hash,
+            deposit,
+        };
+        self.get_auction(asset)?.transaction = Some(tx.clone());
         self.complete_tx(tx).await
     }
 
-    async fn settle_auction(&mut self) -> Result<AuctionEvent, AuctionError> {
-        if self.ended_at < exec::block_timestamp() {
+    fn reveal_bid(
+        &mut self,
+        asset: &Asset,
+        bid: Bid,
+        salt: &[u8; 32],
+    ) -> Result<AuctionEvent, AuctionError> {
+        let auction = self
+            .auctions
+            .get_mut(asset)
+            .ok_or(AuctionError::NoAuction)?;
+        let current_block = exec::block_height();
+        if auction.status != Status::InProcess
+            || current_block < auction.ended_at
+            || current_block >= auction.settlement_block()
+        {
+            return Err(AuctionError::WrongState);
+        }
+
+        let bidder = msg::source();
+        let commitment = *auction
+            .commitments
+            .get(&bidder)
+            .ok_or(AuctionError::WrongBid)?;
+        if bid_hash(bid, salt) != commitment.hash {
+            return Err(AuctionError::WrongHash);
+        }
+        if bid > commitment.deposit {
+            return Err(AuctionError::WrongBid);
+        }
+
+        // the revealed bid beats the current one:
+        // the previous winner gets back the bid
+        // and the new winner gets back the part of the deposit exceeding the bid,
+        // otherwise the whole deposit is returned
+        let refund = if bid > auction.current_bid {
+            if !auction.current_bidder.is_zero() {
+                *self.refunds.entry(auction.current_bidder).or_default() += auction.current_bid;
+            }
+            auction.current_bid = bid;
+            auction.current_bidder = bidder;
+            commitment.deposit - bid
+        } else {
+            commitment.deposit
+        };
+        if refund > 0 {
+            *self.refunds.entry(bidder).or_default() += refund;
+        }
+
+        auction.commitments.remove(&bidder);
+        auction.bids.push(BidRecord {
+            bidder,
+            amount: bid,
+            block: current_block,
+            timestamp: exec::block_timestamp(),
+            transaction_id: None,
+        });
+        Ok(AuctionEvent::BidRevealed { asset: *asset, bid })
+    }
+
+    async fn settle_auction(&mut self, asset: &Asset) -> Result<AuctionEvent, AuctionError> {
+        let auction = self.get_auction(asset)?;
+        // the auction can be settled only after its end
+        if auction.status != Status::InProcess || exec::block_height() < auction.settlement_block()
+        {
             return Err(AuctionError::WrongState);
         }
 
-        // It is possible that there is a pending transaction `MakeBid`
-        if let Some(tx) = self.transaction.clone() {
+        // It is possible that there is a pending bid transaction
+        if let Some(tx) = auction.transaction.clone() {
             match tx {
-                Transaction::MakeBid { .. } => {
-                    _ = self.complete_tx(tx).await;
-                }
                 Transaction::SettleAuction { .. } => {
                     return self.complete_tx(tx).await;
                 }
-                _ => {
+                Transaction::StartAuction { .. } | Transaction::CancelAuction { .. } => {
                     return Err(AuctionError::WrongTx);
                 }
+                _ => {
+                    _ = self.complete_tx(tx).await;
+                }
             }
         }
 
         let transaction_id = self.transaction_id;
-        self.transaction_id = self.transaction_id.wrapping_add(1);
+        self.transaction_id = self.transaction_id.wrapping_add(3);
 
-        let tx = Transaction::SettleAuction { transaction_id };
-        self.transaction = Some(tx.clone());
+        let tx = Transaction::SettleAuction {
+            asset: *asset,
+            transaction_id,
+        };
+        self.get_auction(asset)?.transaction = Some(tx.clone());
         self.complete_tx(tx).await
     }
 
     async fn complete_tx(&mut self, tx: Transaction) -> Result<AuctionEvent, AuctionError> {
+        let ft_contract_id = self.ft_contract_id;
+        let fee_collector = self.fee_collector;
+        let fee_percent = self.fee_percent;
+        let refunds = &mut self.refunds;
+        let auction = self
+            .auctions
+            .get_mut(&tx.asset())
+            .ok_or(AuctionError::NoAuction)?;
         match tx {
             Transaction::StartAuction {
-                bid,
+                asset,
+                transaction_id,
+                seller,
                 duration,
-                tamagotchi_id,
+                kind,
+                reserve_price,
             } => {
-                let tmg_owner = if let Ok(tmg_owner) = get_owner(&tamagotchi_id).await {
-                    tmg_owner
+                let owner = if let Ok(owner) = get_owner(&asset).await {
+                    owner
                 } else {
-                    self.transaction = None;
+                    auction.transaction = None;
                     return Err(AuctionError::WrongReceivedMessage);
                 };
-                // if tamagotchi owner is already the current contract
-                // we just change its state and start the auction
-                if tmg_owner == exec::program_id() {
-                    self.tamagotchi_id = tamagotchi_id;
-                    self.status = Status::InProcess;
-                    self.current_bid = bid;
-                    self.ended_at = exec::block_timestamp() + duration;
-                    self.transaction = None;
-                    return Ok(AuctionEvent::AuctionStarted);
-                };
 
-                // check that owner starts the auction
-                if tmg_owner != msg::source() {
-                    return Err(AuctionError::NotOwner);
+                // if the asset owner is already the current contract
+                // the previous run of that transaction has already taken the asset
+                // or the seller has approved their attribute to the contract
+                if owner != exec::program_id() {
+                    // check that owner starts the auction
+                    if owner != seller {
+                        auction.transaction = None;
+                        return Err(AuctionError::NotOwner);
+                    }
+
+                    if change_owner(&asset, transaction_id, &seller, &exec::program_id())
+                        .await
+                        .is_err()
+                    {
+                        auction.transaction = None;
+                        return Err(AuctionError::UnableToChangeOwner);
+                    }
                 }
 
-                if change_owner(&self.tamagotchi_id, &exec::program_id())
-                    .await
-                    .is_err()
-                {
-                    self.transaction = None;
-                    Err(AuctionError::UnableToChangeOwner)
+                auction.current_bid = match kind {
+                    AuctionKind::English { minimum_bid, .. }
+                    | AuctionKind::SealedBid { minimum_bid, .. } => minimum_bid,
+                    AuctionKind::Dutch { starting_price, .. } => starting_price,
+                };
+                auction.kind = kind;
+                auction.status = Status::InProcess;
+                auction.current_bidder = ActorId::zero();
+                auction.seller = seller;
+                auction.reserve_price = reserve_price;
+                auction.started_at = exec::block_height();
+                auction.ended_at = auction.started_at + duration;
+                auction.transaction = None;
+                auction.bids.clear();
+                auction.commitments.clear();
+                msg::send_delayed(
+                    exec::program_id(),
+                    AuctionAction::SettleAuction { asset },
+                    0,
+                    auction.settlement_block() - auction.started_at,
+                )
+                .expect("Error in sending a delayed message `AuctionAction::SettleAuction`");
+                Ok(AuctionEvent::AuctionStarted { asset })
+            }
+            Transaction::CancelAuction {
+                asset,
+                transaction_id,
+            } => {
+                let owner = if let Ok(owner) = get_owner(&asset).await {
+                    owner
                 } else {
-                    self.tamagotchi_id = tamagotchi_id;
-                    self.status = Status::InProcess;
-                    self.current_bid = bid;
-                    self.prev_tmg_owner = tmg_owner;
-                    self.ended_at = exec::block_timestamp() + duration;
-                    self.transaction = None;
-                    msg::send_delayed(
-                        exec::program_id(),
-                        AuctionAction::SettleAuction,
-                        0,
-                        duration as u32,
-                    )
-                    .expect("Error in sending a delayed message `AuctionAction::SettleAuction`");
-                    Ok(AuctionEvent::AuctionStarted)
+                    return Err(AuctionError::WrongReceivedMessage);
+                };
+                // the asset may have been returned by the previous run
+                if owner == exec::program_id()
+                    && change_owner(&asset, transaction_id, &exec::program_id(), &auction.seller)
+                        .await
+                        .is_err()
+                {
+                    return Err(AuctionError::RerunTransaction);
                 }
+                close(auction);
+                Ok(AuctionEvent::AuctionCancelled { asset })
             }
             Transaction::MakeBid {
+                asset,
                 transaction_id,
                 bidder,
                 bid,
             } => {
                 if transfer_tokens(
                     transaction_id,
-                    &self.ft_contract_id,
+                    &ft_contract_id,
                     &bidder,
                     &exec::program_id(),
                     bid,
@@ -218,125 +496,403 @@ impl Auction {
                 .await
                 .is_err()
                 {
-                    self.transaction = None;
+                    auction.transaction = None;
                     return Err(AuctionError::UnableToTransferTokens);
                 }
 
                 // if it is not the first bet
-                // we have to return the tokens to the previous bidder
-                // since the tokens are on the auction contract
-                // the transaction can fail only due to lack of gas
-                // it is necessary to rerun the transaction
-                if !self.current_bidder.is_zero()
-                    && transfer_tokens(
-                        transaction_id + 1,
-                        &self.ft_contract_id,
-                        &exec::program_id(),
-                        &self.current_bidder,
-                        self.current_bid,
-                    )
-                    .await
-                    .is_err()
+                // the previous bidder can claim the tokens back
+                if !auction.current_bidder.is_zero() {
+                    *refunds.entry(auction.current_bidder).or_default() += auction.current_bid;
+                }
+
+                auction.current_bid = bid;
+                auction.current_bidder = bidder;
+                auction.transaction = None;
+
+                let current_block = exec::block_height();
+                auction.bids.push(BidRecord {
+                    bidder,
+                    amount: bid,
+                    block: current_block,
+                    timestamp: exec::block_timestamp(),
+                    transaction_id: Some(transaction_id),
+                });
+                match auction.kind {
+                    AuctionKind::English {
+                        buy_now_price: Some(price),
+                        ..
+                    } if bid >= price => auction.ended_at = current_block,
+                    AuctionKind::English {
+                        anti_sniping: Some(AntiSniping { window, extension }),
+                        ..
+                    } if auction.ended_at.saturating_sub(current_block) <= window
+                        && current_block + extension > auction.ended_at =>
+                    {
+                        // the previously sent `SettleAuction` will come too early and fail
+                        auction.ended_at = current_block + extension;
+                        msg::send_delayed(
+                            exec::program_id(),
+                            AuctionAction::SettleAuction { asset },
+                            0,
+                            extension,
+                        )
+                        .expect(
+                            "Error in sending a delayed message `AuctionAction::SettleAuction`",
+                        );
+                        return Ok(AuctionEvent::AuctionExtended {
+                            asset,
+                            bid,
+                            ended_at: auction.ended_at,
+                        });
+                    }
+                    AuctionKind::Dutch { .. } => auction.ended_at = current_block,
+                    _ => {}
+                }
+                Ok(AuctionEvent::BidMade { asset, bid })
+            }
+            Transaction::CommitBid {
+                asset,
+                transaction_id,
+                bidder,
+                hash,
+                deposit,
+            } => {
+                if transfer_tokens(
+                    transaction_id,
+                    &ft_contract_id,
+                    &bidder,
+                    &exec::program_id(),
+                    deposit,
+                )
+                .await
+                .is_err()
                 {
-                    return Err(AuctionError::RerunTransaction);
+                    auction.transaction = None;
+                    return Err(AuctionError::UnableToTransferTokens);
                 }
 
-                self.current_bid = bid;
-                self.current_bidder = bidder;
-                Ok(AuctionEvent::BidMade { bid })
+                auction
+                    .commitments
+                    .insert(bidder, Commitment { hash, deposit });
+                auction.transaction = None;
+                Ok(AuctionEvent::BidCommitted { asset })
             }
-            Transaction::SettleAuction { transaction_id } => {
-                let tmg_owner = if let Ok(tmg_owner) = get_owner(&self.tamagotchi_id).await {
-                    tmg_owner
+            Transaction::SettleAuction {
+                asset,
+                transaction_id,
+            } => {
+                let owner = if let Ok(owner) = get_owner(&asset).await {
+                    owner
                 } else {
                     return Err(AuctionError::WrongReceivedMessage);
                 };
-                if tmg_owner == exec::program_id() {
-                    if self.current_bidder.is_zero() {
-                        if change_owner(&self.tamagotchi_id, &self.prev_tmg_owner)
+                let sold = auction.reserve_met();
+                if owner == exec::program_id() {
+                    // deposits of unrevealed bids are given to the seller
+                    let forfeited: Bid = auction
+                        .commitments
+                        .values()
+                        .map(|commitment| commitment.deposit)
+                        .sum();
+                    let (new_owner, payment) = if sold {
+                        let fee = auction.current_bid * fee_percent as u128 / 100;
+                        if fee > 0
+                            && transfer_tokens(
+                                transaction_id,
+                                &ft_contract_id,
+                                &exec::program_id(),
+                                &fee_collector,
+                                fee,
+                            )
                             .await
                             .is_err()
                         {
                             return Err(AuctionError::RerunTransaction);
-                        };
+                        }
+                        (
+                            auction.current_bidder,
+                            auction.current_bid - fee + forfeited,
+                        )
                     } else {
-                        if transfer_tokens(
-                            transaction_id,
-                            &self.ft_contract_id,
+                        (auction.seller, forfeited)
+                    };
+
+                    if payment > 0
+                        && transfer_tokens(
+                            transaction_id + 1,
+                            &ft_contract_id,
                             &exec::program_id(),
-                            &self.prev_tmg_owner,
-                            self.current_bid,
+                            &auction.seller,
+                            payment,
                         )
                         .await
                         .is_err()
-                        {
-                            return Err(AuctionError::RerunTransaction);
-                        };
+                    {
+                        return Err(AuctionError::RerunTransaction);
+                    };
 
-                        if change_owner(&self.tamagotchi_id, &self.current_bidder)
-                            .await
-                            .is_err()
-                        {
-                            return Err(AuctionError::RerunTransaction);
-                        };
-                    }
+                    if change_owner(&asset, transaction_id + 2, &exec::program_id(), &new_owner)
+                        .await
+                        .is_err()
+                    {
+                        return Err(AuctionError::RerunTransaction);
+                    };
+                }
+                let (bidder, bid) = (auction.current_bidder, auction.current_bid);
+                // the reserve price isn't met so the bid is returned to the bidder
+                if !sold && !bidder.is_zero() {
+                    *refunds.entry(bidder).or_default() += bid;
                 }
-                self.transaction = None;
-                self.prev_tmg_owner = ActorId::zero();
-                self.current_bidder = ActorId::zero();
-                self.status = Status::ReadyToStart;
-                self.ended_at = 0;
+                let record = CompletedAuction {
+                    kind: auction.kind.clone(),
+                    seller: auction.seller,
+                    winner: sold.then_some(bidder),
+                    final_price: if sold { bid } else { 0 },
+                    started_at: auction.started_at,
+                    settled_at: exec::block_height(),
+                    bids: mem::take(&mut auction.bids),
+                };
+                auction.history.push(record);
+                if auction.history.len() > MAX_HISTORY_LEN {
+                    auction.history.remove(0);
+                }
+                close(auction);
 
-                Ok(AuctionEvent::AuctionSettled)
+                if sold || bidder.is_zero() {
+                    Ok(AuctionEvent::AuctionSettled { asset })
+                } else {
+                    Ok(AuctionEvent::ReserveNotMet { asset, bid })
+                }
             }
         }
     }
 
+    async fn claim_refund(&mut self) -> Result<AuctionEvent, AuctionError> {
+        let account = msg::source();
+        // the previous claim may have been interrupted
+        let (transaction_id, amount) = if let Some(claim) = self.refund_claims.get(&account) {
+            *claim
+        } else {
+            let amount = self
+                .refunds
+                .remove(&account)
+                .ok_or(AuctionError::NoRefund)?;
+            let transaction_id = self.transaction_id;
+            self.transaction_id = self.transaction_id.wrapping_add(1);
+            self.refund_claims.insert(account, (transaction_id, amount));
+            (transaction_id, amount)
+        };
+
+        // the tokens are on the auction contract
+        // so the transfer can fail only due to lack of gas
+        if transfer_tokens(
+            transaction_id,
+            &self.ft_contract_id,
+            &exec::program_id(),
+            &account,
+            amount,
+        )
+        .await
+        .is_err()
+        {
+            return Err(AuctionError::RerunTransaction);
+        }
+
+        self.refund_claims.remove(&account);
+        Ok(AuctionEvent::RefundClaimed { amount })
+    }
+
+    fn allow_collections(
+        &mut self,
+        collections: Vec<ActorId>,
+    ) -> Result<AuctionEvent, AuctionError> {
+        self.check_admin()?;
+        self.allowlist.extend(collections);
+        Ok(AuctionEvent::AllowlistUpdated)
+    }
+
+    fn disallow_collections(
+        &mut self,
+        collections: Vec<ActorId>,
+    ) -> Result<AuctionEvent, AuctionError> {
+        self.check_admin()?;
+        for collection in collections {
+            self.allowlist.remove(&collection);
+        }
+        Ok(AuctionEvent::AllowlistUpdated)
+    }
+
+    fn set_admin(&mut self, admin: ActorId) -> Result<AuctionEvent, AuctionError> {
+        self.check_admin()?;
+        self.admin = admin;
+        Ok(AuctionEvent::AdminChanged(admin))
+    }
+
+    fn check_admin(&self) -> Result<(), AuctionError> {
+        if self.admin != msg::source() {
+            return Err(AuctionError::NotAdmin);
+        }
+        Ok(())
+    }
+
     fn make_reservation(&mut self) -> Result<AuctionEvent, AuctionError> {
-        let reservation_id = ReservationId::reserve(RESERVATION_AMOUNT, RESERVATION_DURATION)
+        let id = ReservationId::reserve(RESERVATION_AMOUNT, RESERVATION_DURATION)
             .expect("reservation across executions");
-        self.reservations.push(reservation_id);
+        self.reservations.push(Reservation {
+            id,
+            created_at: exec::block_height(),
+        });
         Ok(AuctionEvent::ReservationMade)
     }
+
+    // Discards the expired reservations and makes a new one
+    // if the pool isn't full and the message has enough gas for it.
+    fn top_up_reservations(&mut self) {
+        self.discard_expired_reservations();
+        if self.reservations.len() >= RESERVATION_POOL_SIZE
+            || exec::gas_available() < RESERVATION_AMOUNT + RESERVATION_GAS_MARGIN
+        {
+            return;
+        }
+        if let Ok(id) = ReservationId::reserve(RESERVATION_AMOUNT, RESERVATION_DURATION) {
+            self.reservations.push(Reservation {
+                id,
+                created_at: exec::block_height(),
+            });
+        }
+    }
+
+    fn discard_expired_reservations(&mut self) {
+        let current_block = exec::block_height();
+        self.reservations
+            .retain(|reservation| reservation.expires_at() > current_block);
+    }
+
+    // Checks that the auction accepts bids
+    fn check_bidding(&mut self, asset: &Asset) -> Result<(), AuctionError> {
+        let auction = self.get_auction(asset)?;
+        if auction.status != Status::InProcess || exec::block_height() >= auction.ended_at {
+            return Err(AuctionError::WrongState);
+        }
+        Ok(())
+    }
+
+    // Completes the pending bid transaction of the auction (if any).
+    // Returns its result if `is_same` recognizes the transaction as the one being sent again.
+    async fn complete_pending_bid(
+        &mut self,
+        asset: &Asset,
+        is_same: impl Fn(&Transaction) -> bool,
+    ) -> Option<Result<AuctionEvent, AuctionError>> {
+        let tx = self.get_auction(asset).ok()?.transaction.clone()?;
+        match tx {
+            Transaction::StartAuction { .. }
+            | Transaction::CancelAuction { .. }
+            | Transaction::SettleAuction { .. } => Some(Err(AuctionError::WrongTx)),
+            _ => {
+                let is_same = is_same(&tx);
+                let result = self.complete_tx(tx).await;
+                is_same.then_some(result)
+            }
+        }
+    }
+
+    fn get_auction(&mut self, asset: &Asset) -> Result<&mut AuctionInfo, AuctionError> {
+        self.auctions.get_mut(asset).ok_or(AuctionError::NoAuction)
+    }
 }
 
-#[gstd::async_main]
+#[gstd::async_main(handle_signal = my_handle_signal)]
 async fn main() {
     let action: AuctionAction = msg::load().expect("Unable to decode `AuctionAction`");
     let auction = unsafe { AUCTION.get_or_insert(Default::default()) };
+    if let Some(asset) = tx_asset(&action) {
+        auction.tx_messages.insert(msg::id(), asset);
+    }
     let reply = match action {
         AuctionAction::StartAuction {
-            tamagotchi_id,
-            minimum_bid,
+            asset,
             duration,
+            kind,
+            reserve_price,
         } => {
             system_reserve_gas();
             auction
-                .start_auction(&tamagotchi_id, minimum_bid, duration)
+                .start_auction(&asset, duration, kind, reserve_price)
                 .await
         }
-        AuctionAction::MakeBid { bid } => {
+        AuctionAction::CancelAuction { asset } => {
+            system_reserve_gas();
+            auction.cancel_auction(&asset).await
+        }
+        AuctionAction::MakeBid { asset, bid } => {
+            system_reserve_gas();
+            auction.make_bid(&asset, bid).await
+        }
+        AuctionAction::CommitBid {
+            asset,
+            hash,
+            deposit,
+        } => {
+            system_reserve_gas();
+            auction.commit_bid(&asset, hash, deposit).await
+        }
+        AuctionAction::RevealBid { asset, bid, salt } => auction.reveal_bid(&asset, bid, &salt),
+        AuctionAction::SettleAuction { asset } => {
             system_reserve_gas();
-            auction.make_bid(bid).await
+            auction.settle_auction(&asset).await
         }
-        AuctionAction::SettleAuction => {
+        AuctionAction::ClaimRefund => {
             system_reserve_gas();
-            auction.settle_auction().await
+            auction.claim_refund().await
         }
         AuctionAction::MakeReservation => auction.make_reservation(),
-        AuctionAction::CompleteTx(tx) => {
-            if let Some(_tx) = &auction.transaction {
+        AuctionAction::CompleteTx(tx) => match auction.get_auction(&tx.asset()) {
+            Ok(AuctionInfo {
+                transaction: Some(_tx),
+                ..
+            }) => {
                 if tx == _tx.clone() {
                     auction.complete_tx(tx).await
                 } else {
                     Err(AuctionError::WrongTx)
                 }
-            } else {
-                Err(AuctionError::NoTx)
             }
+            _ => Err(AuctionError::NoTx),
+        },
+        AuctionAction::AllowCollections(collections) => auction.allow_collections(collections),
+        AuctionAction::DisallowCollections(collections) => {
+            auction.disallow_collections(collections)
         }
+        AuctionAction::SetAdmin(admin) => auction.set_admin(admin),
     };
     msg::reply(reply, 0).expect("Failed to encode or reply with `Result<MarketEvent, MarketErr>`");
+    auction.tx_messages.remove(&msg::id());
+    auction.top_up_reservations();
+}
+
+// Returns the asset whose transaction the action may leave pending.
+fn tx_asset(action: &AuctionAction) -> Option<Asset> {
+    match action {
+        AuctionAction::StartAuction { asset, .. }
+        | AuctionAction::CancelAuction { asset }
+        | AuctionAction::MakeBid { asset, .. }
+        | AuctionAction::CommitBid { asset, .. }
+        | AuctionAction::SettleAuction { asset } => Some(*asset),
+        AuctionAction::CompleteTx(tx) => Some(tx.asset()),
+        _ => None,
+    }
+}
+
+// Resets the auction after the asset has left the contract.
+fn close(auction: &mut AuctionInfo) {
+    auction.transaction = None;
+    auction.seller = ActorId::zero();
+    auction.current_bidder = ActorId::zero();
+    auction.commitments.clear();
+    auction.status = Status::ReadyToStart;
+    auction.ended_at = 0;
 }
 
 fn system_reserve_gas() {
@@ -377,37 +933,263 @@ async fn transfer_tokens(
     }
 }
 
-async fn get_owner(tamagotchi_id: &TamagotchiId) -> Result<ActorId, AuctionError> {
-    let reply = msg::send_for_reply_as(*tamagotchi_id, TmgAction::Owner, 0, 0)
-        .expect("Error in sending a message `TmgAction::Owner` to Tamagotchi contract")
-        .await;
-    match reply {
-        Ok(TmgEvent::Owner(tmg_owner)) => Ok(tmg_owner),
-        _ => Err(AuctionError::WrongReceivedMessage),
+// Checks that the seller can put the asset up for auction.
+// Only the holder sells an attribute, that is approved to the contract before the start.
+async fn check_seller(asset: &Asset, seller: &ActorId) -> Result<(), AuctionError> {
+    let owner = match asset {
+        Asset::Attribute { holder, .. } => *holder,
+        _ => get_owner(asset).await?,
+    };
+    if owner != *seller {
+        return Err(AuctionError::NotOwner);
+    }
+    Ok(())
+}
+
+// An attribute stays with its holder, so it's owned by the contract while it's approved
+// to the contract and by the holder while it isn't approved to anyone.
+async fn get_owner(asset: &Asset) -> Result<ActorId, AuctionError> {
+    match asset {
+        Asset::Tamagotchi(tamagotchi_id) => {
+            let reply = msg::send_for_reply_as(*tamagotchi_id, TmgAction::Owner, 0, 0)
+                .expect("Error in sending a message `TmgAction::Owner` to Tamagotchi contract")
+                .await;
+            match reply {
+                Ok(TmgEvent::Owner(tmg_owner)) => Ok(tmg_owner),
+                _ => Err(AuctionError::WrongReceivedMessage),
+            }
+        }
+        Asset::Nft {
+            contract_id,
+            token_id,
+        } => {
+            let reply = msg::send_for_reply_as(
+                *contract_id,
+                NFTAction::Owner {
+                    token_id: *token_id,
+                },
+                0,
+                0,
+            )
+            .expect("Error in sending a message `NFTAction::Owner` to NFT contract")
+            .await;
+            match reply {
+                Ok(NFTEvent::Owner { owner, .. }) => Ok(owner),
+                _ => Err(AuctionError::WrongReceivedMessage),
+            }
+        }
+        Asset::Attribute {
+            store_id,
+            attribute_id,
+            holder,
+        } => {
+            let reply = msg::send_for_reply_as(
+                *store_id,
+                StoreAction::GetApproval {
+                    holder: *holder,
+                    attribute_id: *attribute_id,
+                },
+                0,
+                0,
+            )
+            .expect("Error in sending a message `StoreAction::GetApproval` to store contract")
+            .await;
+            match reply {
+                Ok(StoreEvent::Approval { spender: None }) => {}
+                Ok(StoreEvent::Approval {
+                    spender: Some(spender),
+                }) => {
+                    return Ok(if spender == exec::program_id() {
+                        spender
+                    } else {
+                        ActorId::zero()
+                    })
+                }
+                _ => return Err(AuctionError::WrongReceivedMessage),
+            }
+
+            let reply = msg::send_for_reply_as(
+                *store_id,
+                StoreAction::GetAttributes {
+                    tamagotchi_id: *holder,
+                },
+                0,
+                0,
+            )
+            .expect("Error in sending a message `StoreAction::GetAttributes` to store contract")
+            .await;
+            match reply {
+                Ok(StoreEvent::Attributes { attributes }) if attributes.contains(attribute_id) => {
+                    Ok(*holder)
+                }
+                Ok(StoreEvent::Attributes { .. }) => Ok(ActorId::zero()),
+                _ => Err(AuctionError::WrongReceivedMessage),
+            }
+        }
     }
 }
 
-async fn change_owner(tamagotchi_id: &TamagotchiId, new_owner: &ActorId) -> Result<TmgEvent> {
-    msg::send_for_reply_as::<_, TmgEvent>(*tamagotchi_id, TmgAction::Transfer(*new_owner), 0, 0)
-        .expect("Error in sending a message `TmgAction::ChangeOwner` to Tamagotchi contract")
+// The NFT contract executes each transaction only once
+// and the store replies success to a transfer or a release that has already been made,
+// so the transfer can be safely repeated.
+async fn change_owner(
+    asset: &Asset,
+    transaction_id: TransactionId,
+    current_owner: &ActorId,
+    new_owner: &ActorId,
+) -> Result<(), ()> {
+    match asset {
+        Asset::Tamagotchi(tamagotchi_id) => msg::send_for_reply_as::<_, TmgEvent>(
+            *tamagotchi_id,
+            TmgAction::Transfer(*new_owner),
+            0,
+            0,
+        )
+        .expect("Error in sending a message `TmgAction::Transfer` to Tamagotchi contract")
         .await
+        .map(|_| ())
+        .map_err(|_| ()),
+        Asset::Nft {
+            contract_id,
+            token_id,
+        } => {
+            let reply = msg::send_for_reply_as::<_, NFTEvent>(
+                *contract_id,
+                NFTAction::Transfer {
+                    transaction_id,
+                    to: *new_owner,
+                    token_id: *token_id,
+                },
+                0,
+                0,
+            )
+            .expect("Error in sending a message `NFTAction::Transfer` to NFT contract")
+            .await;
+            match reply {
+                Ok(NFTEvent::Transfer(_)) => Ok(()),
+                _ => Err(()),
+            }
+        }
+        Asset::Attribute {
+            store_id,
+            attribute_id,
+            holder,
+        } => {
+            // the holder approves the attribute to the contract by themselves
+            if *current_owner != exec::program_id() {
+                return Err(());
+            }
+            // the attribute stays with the holder, so returning it only cancels the approval
+            let action = if new_owner == holder {
+                StoreAction::ReleaseAttribute {
+                    holder: *holder,
+                    attribute_id: *attribute_id,
+                }
+            } else {
+                StoreAction::TransferAttribute {
+                    from: *holder,
+                    to: *new_owner,
+                    attribute_id: *attribute_id,
+                }
+            };
+            let reply = msg::send_for_reply_as::<_, StoreEvent>(*store_id, action, 0, 0)
+                .expect("Error in sending a message `StoreAction` to store contract")
+                .await;
+            match reply {
+                Ok(StoreEvent::AttributeTransferred { .. })
+                | Ok(StoreEvent::AttributeReleased { .. }) => Ok(()),
+                _ => Err(()),
+            }
+        }
+    }
 }
 
 #[no_mangle]
-extern "C" fn my_handle_signal() {
+extern "C" fn init() {
+    let InitAuction {
+        ft_contract_id,
+        fee_collector,
+        fee_percent,
+    } = msg::load().expect("Unable to decode `InitAuction`");
+    assert!(fee_percent <= 100, "The fee can't exceed 100 percent");
+    let auction = Auction {
+        admin: msg::source(),
+        ft_contract_id,
+        fee_collector,
+        fee_percent,
+        ..Default::default()
+    };
+    unsafe { AUCTION = Some(auction) };
+}
+
+#[no_mangle]
+extern "C" fn state() {
     let auction = unsafe { AUCTION.get_or_insert(Default::default()) };
-    if let Some(tx) = &auction.transaction {
-        let reservation_id = if !auction.reservations.is_empty() {
-            auction.reservations.remove(0)
-        } else {
-            return;
-        };
-        msg::send_from_reservation(
-            reservation_id,
-            exec::program_id(),
-            AuctionAction::CompleteTx(tx.clone()),
-            0,
-        )
-        .expect("Failed to send message");
+    let state = AuctionState {
+        admin: auction.admin,
+        ft_contract_id: auction.ft_contract_id,
+        fee_collector: auction.fee_collector,
+        fee_percent: auction.fee_percent,
+        transaction_id: auction.transaction_id,
+        auctions: auction
+            .auctions
+            .iter()
+            .map(|(asset, info)| (*asset, info.clone()))
+            .collect(),
+        allowlist: auction.allowlist.iter().copied().collect(),
+        reservations: auction
+            .reservations
+            .iter()
+            .map(|reservation| ReservationInfo {
+                amount: RESERVATION_AMOUNT,
+                created_at: reservation.created_at,
+                expires_at: reservation.expires_at(),
+            })
+            .collect(),
+        refunds: auction
+            .refunds
+            .iter()
+            .map(|(account, refund)| (*account, *refund))
+            .collect(),
+        refund_claims: auction
+            .refund_claims
+            .iter()
+            .map(|(account, claim)| (*account, *claim))
+            .collect(),
+    };
+    msg::reply(state, 0).expect("Failed to share state");
+}
+
+fn my_handle_signal() {
+    let auction = unsafe { AUCTION.get_or_insert(Default::default()) };
+    auction.discard_expired_reservations();
+    // resend the pending transaction of the asset the failed message was working on
+    let asset = if let Some(asset) = msg::signal_from()
+        .ok()
+        .and_then(|message_id| auction.tx_messages.remove(&message_id))
+    {
+        asset
+    } else {
+        return;
+    };
+    let tx = if let Some(tx) = auction
+        .auctions
+        .get(&asset)
+        .and_then(|auction| auction.transaction.clone())
+    {
+        tx
+    } else {
+        return;
+    };
+    if auction.reservations.is_empty() {
+        return;
     }
+    let reservation = auction.reservations.remove(0);
+    msg::send_from_reservation(
+        reservation.id,
+        exec::program_id(),
+        AuctionAction::CompleteTx(tx),
+        0,
+    )
+    .expect("Failed to send message");
 }
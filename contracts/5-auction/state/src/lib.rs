@@ -0,0 +1,103 @@
+#![no_std]
+
+use auction_io::*;
+use gmeta::metawasm;
+use gstd::{prelude::*, ActorId};
+
+#[metawasm]
+pub mod metafns {
+    pub type State = AuctionState;
+
+    pub fn auction(state: State, asset: Asset) -> Option<AuctionInfo> {
+        state.auction(&asset).cloned()
+    }
+
+    /// Returns the assets that are currently being sold.
+    pub fn active_auctions(state: State) -> Vec<Asset> {
+        state
+            .auctions
+            .into_iter()
+            .filter(|(_, auction)| auction.status == Status::InProcess)
+            .map(|(asset, _)| asset)
+            .collect()
+    }
+
+    pub fn is_allowed(state: State, asset: Asset) -> bool {
+        state.is_allowed(&asset)
+    }
+
+    pub fn current_bid(state: State, asset: Asset) -> Option<(ActorId, Bid)> {
+        state
+            .auction(&asset)
+            .map(|auction| (auction.current_bidder, auction.current_bid))
+    }
+
+    /// Returns `limit` bids of the current auction starting from `offset`.
+    pub fn bid_history(state: State, asset: Asset, offset: u32, limit: u32) -> Vec<BidRecord> {
+        state
+            .auction(&asset)
+            .map(|auction| page(&auction.bids, offset, limit))
+            .unwrap_or_default()
+    }
+
+    /// Returns `limit` settled auctions of the asset starting from `offset`
+    /// (the oldest auction goes first).
+    pub fn auction_history(
+        state: State,
+        asset: Asset,
+        offset: u32,
+        limit: u32,
+    ) -> Vec<CompletedAuction> {
+        state
+            .auction(&asset)
+            .map(|auction| page(&auction.history, offset, limit))
+            .unwrap_or_default()
+    }
+
+    pub fn min_next_bid(state: State, asset: Asset) -> Option<Bid> {
+        state
+            .auction(&asset)
+            .and_then(|auction| auction.min_next_bid())
+    }
+
+    /// Returns the price of the Dutch auction at the given block.
+    pub fn dutch_price(state: State, asset: Asset, current_block: BlockNumber) -> Option<Bid> {
+        state
+            .auction(&asset)
+            .and_then(|auction| auction.dutch_price(current_block))
+    }
+
+    /// Returns the accounts that have committed bids in the sealed-bid auction
+    /// but haven't revealed them yet.
+    pub fn unrevealed_bidders(state: State, asset: Asset) -> Vec<ActorId> {
+        state
+            .auction(&asset)
+            .map(|auction| auction.commitments.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns how many blocks are left until the auction can be settled
+    /// at the given block height.
+    pub fn blocks_remaining(state: State, asset: Asset, current_block: BlockNumber) -> BlockNumber {
+        match state.auction(&asset) {
+            Some(auction) if auction.status == Status::InProcess => {
+                auction.settlement_block().saturating_sub(current_block)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Returns the tokens the account can claim back with `AuctionAction::ClaimRefund`.
+    pub fn refund_of(state: State, account: ActorId) -> Bid {
+        state.refund_of(&account)
+    }
+
+    /// Returns the number of reservations the signal handler can still use at the given block.
+    pub fn usable_reservations(state: State, current_block: BlockNumber) -> u32 {
+        state
+            .reservations
+            .iter()
+            .filter(|reservation| reservation.expires_at > current_block)
+            .count() as u32
+    }
+}
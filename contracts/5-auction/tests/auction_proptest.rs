@@ -0,0 +1,72 @@
+use auction_io::*;
+use gstd::ActorId;
+use proptest::prelude::*;
+use utils::*;
+
+mod utils;
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn random_bids(bids in prop::collection::vec((0..BIDDERS.len(), 0..2 * MIN_BID), 1..10)) {
+        let sys = init_system();
+        assert!(!start_auction(&sys, SELLER, TMG_ID).main_failed());
+
+        let mut current_bid = MIN_BID;
+        let mut current_bidder = None;
+        let mut paid = [0; BIDDERS.len()];
+        for (index, bid) in bids {
+            let bidder = BIDDERS[index];
+            let res = make_bid(&sys, bidder, TMG_ID, bid);
+            // the first bid can be the minimum one
+            if bid > current_bid || (current_bidder.is_none() && bid == current_bid) {
+                let reply = Ok(AuctionEvent::BidMade {
+                    asset: tmg(TMG_ID),
+                    bid,
+                });
+                prop_assert!(replied(&res, bidder, reply));
+                current_bid = bid;
+                current_bidder = Some(bidder);
+                paid[index] += bid;
+            } else {
+                prop_assert!(replied(&res, bidder, Err(AuctionError::WrongBid)));
+            }
+
+            // all bids stay on the auction and all but the highest one are refundable
+            let auction = auction_info(&sys, TMG_ID);
+            prop_assert_eq!(auction.current_bid, current_bid);
+            for (index, bidder) in BIDDERS.into_iter().enumerate() {
+                let winning = if current_bidder == Some(bidder) { current_bid } else { 0 };
+                prop_assert_eq!(balance_of(&sys, bidder), BALANCE - paid[index]);
+                prop_assert_eq!(refund_of(&sys, bidder), paid[index] - winning);
+            }
+            prop_assert_eq!(balance_of(&sys, AUCTION_ID), paid.iter().sum::<u128>());
+        }
+
+        sys.spend_blocks(DURATION);
+        for (index, bidder) in BIDDERS.into_iter().enumerate() {
+            let res = claim_refund(&sys, bidder);
+            let winning = if current_bidder == Some(bidder) { current_bid } else { 0 };
+            if paid[index] > winning {
+                let reply = Ok(AuctionEvent::RefundClaimed {
+                    amount: paid[index] - winning,
+                });
+                prop_assert!(replied(&res, bidder, reply));
+            } else {
+                prop_assert!(replied(&res, bidder, Err(AuctionError::NoRefund)));
+            }
+            prop_assert_eq!(balance_of(&sys, bidder), BALANCE - winning);
+        }
+
+        prop_assert_eq!(auction_info(&sys, TMG_ID).status, Status::ReadyToStart);
+        prop_assert_eq!(balance_of(&sys, AUCTION_ID), 0);
+        if let Some(winner) = current_bidder {
+            prop_assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(winner));
+            prop_assert_eq!(balance_of(&sys, SELLER), current_bid);
+        } else {
+            prop_assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(SELLER));
+            prop_assert_eq!(balance_of(&sys, SELLER), 0);
+        }
+    }
+}
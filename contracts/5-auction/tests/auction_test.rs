@@ -0,0 +1,773 @@
+use auction_io::*;
+use gstd::ActorId;
+use utils::*;
+
+mod utils;
+
+#[test]
+fn start() {
+    let sys = init_system();
+
+    let res = start_auction(&sys, SELLER, TMG_ID);
+    assert!(replied(
+        &res,
+        SELLER,
+        Ok(AuctionEvent::AuctionStarted { asset: tmg(TMG_ID) })
+    ));
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(AUCTION_ID));
+
+    let auction = auction_info(&sys, TMG_ID);
+    assert_eq!(auction.status, Status::InProcess);
+    assert_eq!(auction.current_bid, MIN_BID);
+    assert_eq!(auction.seller, ActorId::from(SELLER));
+
+    // must fail since the auction is already in process
+    let res = start_auction(&sys, SELLER, TMG_ID);
+    assert!(replied(&res, SELLER, Err(AuctionError::WrongState)));
+}
+
+#[test]
+fn start_auction_by_not_owner() {
+    let sys = init_system();
+
+    let res = start_auction(&sys, BIDDERS[0], TMG_ID);
+    assert!(replied(&res, BIDDERS[0], Err(AuctionError::NotOwner)));
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(SELLER));
+    // the failed start must not leave an auction in the state
+    assert!(!has_auction(&sys, TMG_ID));
+
+    // the failed attempt must not block the owner
+    let res = start_auction(&sys, SELLER, TMG_ID);
+    assert!(replied(
+        &res,
+        SELLER,
+        Ok(AuctionEvent::AuctionStarted { asset: tmg(TMG_ID) })
+    ));
+}
+
+#[test]
+fn concurrent_auctions_transfer_right_tamagotchis() {
+    let sys = init_system();
+
+    assert!(!start_auction(&sys, SELLER, TMG_ID).main_failed());
+    assert!(!start_auction(&sys, SELLER, SECOND_TMG_ID).main_failed());
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(AUCTION_ID));
+    assert_eq!(tmg_owner(&sys, SECOND_TMG_ID), ActorId::from(AUCTION_ID));
+
+    assert!(!make_bid(&sys, BIDDERS[0], TMG_ID, MIN_BID + 1).main_failed());
+    assert!(!make_bid(&sys, BIDDERS[1], SECOND_TMG_ID, MIN_BID + 2).main_failed());
+
+    sys.spend_blocks(DURATION);
+
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(BIDDERS[0]));
+    assert_eq!(tmg_owner(&sys, SECOND_TMG_ID), ActorId::from(BIDDERS[1]));
+    assert_eq!(balance_of(&sys, SELLER), 2 * MIN_BID + 3);
+}
+
+#[test]
+fn settle_before_end() {
+    let sys = init_system();
+    assert!(!start_auction(&sys, SELLER, TMG_ID).main_failed());
+    assert!(!make_bid(&sys, BIDDERS[0], TMG_ID, MIN_BID + 1).main_failed());
+
+    let res = settle_auction(&sys, BIDDERS[0], TMG_ID);
+    assert!(replied(&res, BIDDERS[0], Err(AuctionError::WrongState)));
+
+    sys.spend_blocks(DURATION / 2);
+
+    let res = settle_auction(&sys, SELLER, TMG_ID);
+    assert!(replied(&res, SELLER, Err(AuctionError::WrongState)));
+
+    // nothing has been transferred
+    let auction = auction_info(&sys, TMG_ID);
+    assert_eq!(auction.status, Status::InProcess);
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(AUCTION_ID));
+    assert_eq!(balance_of(&sys, AUCTION_ID), MIN_BID + 1);
+    assert_eq!(balance_of(&sys, SELLER), 0);
+}
+
+#[test]
+fn settle_after_end() {
+    let sys = init_system();
+    assert!(!start_auction(&sys, SELLER, TMG_ID).main_failed());
+
+    let res = make_bid(&sys, BIDDERS[0], TMG_ID, MIN_BID + 1);
+    assert!(replied(
+        &res,
+        BIDDERS[0],
+        Ok(AuctionEvent::BidMade {
+            asset: tmg(TMG_ID),
+            bid: MIN_BID + 1,
+        })
+    ));
+    let res = make_bid(&sys, BIDDERS[1], TMG_ID, MIN_BID + 10);
+    assert!(!res.main_failed());
+
+    // the outbid tokens stay on the auction until they are claimed
+    assert_eq!(balance_of(&sys, BIDDERS[0]), BALANCE - MIN_BID - 1);
+    assert_eq!(refund_of(&sys, BIDDERS[0]), MIN_BID + 1);
+
+    // the delayed message settles the auction
+    sys.spend_blocks(DURATION);
+
+    let auction = auction_info(&sys, TMG_ID);
+    assert_eq!(auction.status, Status::ReadyToStart);
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(BIDDERS[1]));
+    assert_eq!(balance_of(&sys, SELLER), MIN_BID + 10);
+    assert_eq!(balance_of(&sys, BIDDERS[1]), BALANCE - MIN_BID - 10);
+    assert_eq!(balance_of(&sys, AUCTION_ID), MIN_BID + 1);
+
+    let res = claim_refund(&sys, BIDDERS[0]);
+    assert!(replied(
+        &res,
+        BIDDERS[0],
+        Ok(AuctionEvent::RefundClaimed {
+            amount: MIN_BID + 1
+        })
+    ));
+    assert_eq!(balance_of(&sys, BIDDERS[0]), BALANCE);
+    assert_eq!(balance_of(&sys, AUCTION_ID), 0);
+
+    // must fail since the refund has already been claimed
+    let res = claim_refund(&sys, BIDDERS[0]);
+    assert!(replied(&res, BIDDERS[0], Err(AuctionError::NoRefund)));
+
+    // the auction can't be settled twice and doesn't accept bids anymore
+    let res = settle_auction(&sys, SELLER, TMG_ID);
+    assert!(replied(&res, SELLER, Err(AuctionError::WrongState)));
+    let res = make_bid(&sys, BIDDERS[2], TMG_ID, MIN_BID + 100);
+    assert!(replied(&res, BIDDERS[2], Err(AuctionError::WrongState)));
+}
+
+#[test]
+fn settle_without_bids() {
+    let sys = init_system();
+    assert!(!start_auction(&sys, SELLER, TMG_ID).main_failed());
+
+    sys.spend_blocks(DURATION);
+
+    assert_eq!(auction_info(&sys, TMG_ID).status, Status::ReadyToStart);
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(SELLER));
+
+    // the Tamagotchi can be put up for auction again
+    let res = start_auction(&sys, SELLER, TMG_ID);
+    assert!(replied(
+        &res,
+        SELLER,
+        Ok(AuctionEvent::AuctionStarted { asset: tmg(TMG_ID) })
+    ));
+}
+
+#[test]
+fn wrong_bids() {
+    let sys = init_system();
+
+    // must fail since the auction doesn't exist
+    let res = make_bid(&sys, BIDDERS[0], TMG_ID, MIN_BID + 1);
+    assert!(replied(&res, BIDDERS[0], Err(AuctionError::NoAuction)));
+
+    assert!(!start_auction(&sys, SELLER, TMG_ID).main_failed());
+
+    // must fail since the bid is less than the minimum one
+    let res = make_bid(&sys, BIDDERS[0], TMG_ID, MIN_BID - 1);
+    assert!(replied(&res, BIDDERS[0], Err(AuctionError::WrongBid)));
+
+    // must fail since the bidder doesn't have enough tokens
+    let res = make_bid(&sys, BIDDERS[0], TMG_ID, BALANCE + 1);
+    assert!(replied(
+        &res,
+        BIDDERS[0],
+        Err(AuctionError::UnableToTransferTokens)
+    ));
+    assert!(auction_info(&sys, TMG_ID).current_bidder.is_zero());
+}
+
+#[test]
+fn buy_now() {
+    let sys = init_system();
+    let kind = AuctionKind::English {
+        minimum_bid: MIN_BID,
+        buy_now_price: Some(2 * MIN_BID),
+        bid_increment: BidIncrement::Absolute(1),
+        anti_sniping: None,
+    };
+    assert!(!start_auction_with_kind(&sys, SELLER, TMG_ID, kind).main_failed());
+
+    assert!(!make_bid(&sys, BIDDERS[0], TMG_ID, MIN_BID + 1).main_failed());
+    assert_eq!(auction_info(&sys, TMG_ID).status, Status::InProcess);
+
+    // the auction is settled right after the bid reaching the buy-now price
+    let res = make_bid(&sys, BIDDERS[1], TMG_ID, 2 * MIN_BID);
+    assert!(replied(
+        &res,
+        BIDDERS[1],
+        Ok(AuctionEvent::AuctionSettled { asset: tmg(TMG_ID) })
+    ));
+    assert_eq!(auction_info(&sys, TMG_ID).status, Status::ReadyToStart);
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(BIDDERS[1]));
+    assert_eq!(balance_of(&sys, SELLER), 2 * MIN_BID);
+    assert_eq!(refund_of(&sys, BIDDERS[0]), MIN_BID + 1);
+}
+
+#[test]
+fn bid_after_pending_buy_now() {
+    let sys = init_system();
+    let kind = AuctionKind::English {
+        minimum_bid: MIN_BID,
+        buy_now_price: Some(2 * MIN_BID),
+        bid_increment: BidIncrement::Absolute(1),
+        anti_sniping: None,
+    };
+    assert!(!start_auction_with_kind(&sys, SELLER, TMG_ID, kind).main_failed());
+
+    // the token transfer of the buy-now bid stays unanswered
+    // so the bid remains the pending transaction of the auction
+    let res = make_bid(&sys, SLOW_BIDDER, TMG_ID, 2 * MIN_BID);
+    assert!(!res.main_failed());
+    assert!(auction_info(&sys, TMG_ID).transaction.is_some());
+
+    // the next bid completes the buy-now bid and must fail since the asset is bought
+    let res = make_bid(&sys, BIDDERS[0], TMG_ID, 3 * MIN_BID);
+    assert!(replied(&res, BIDDERS[0], Err(AuctionError::WrongState)));
+    let auction = auction_info(&sys, TMG_ID);
+    assert_eq!(auction.current_bidder, ActorId::from(SLOW_BIDDER));
+    assert_eq!(auction.current_bid, 2 * MIN_BID);
+    assert_eq!(balance_of(&sys, BIDDERS[0]), BALANCE);
+
+    let res = settle_auction(&sys, SELLER, TMG_ID);
+    assert!(replied(
+        &res,
+        SELLER,
+        Ok(AuctionEvent::AuctionSettled { asset: tmg(TMG_ID) })
+    ));
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(SLOW_BIDDER));
+    assert_eq!(balance_of(&sys, SELLER), 2 * MIN_BID);
+}
+
+#[test]
+fn bid_increment() {
+    let sys = init_system();
+    let kind = AuctionKind::English {
+        minimum_bid: MIN_BID,
+        buy_now_price: None,
+        bid_increment: BidIncrement::Percent(10),
+        anti_sniping: None,
+    };
+    assert!(!start_auction_with_kind(&sys, SELLER, TMG_ID, kind).main_failed());
+    assert_eq!(auction_info(&sys, TMG_ID).min_next_bid(), Some(MIN_BID));
+
+    // the first bid can be the minimum one
+    let res = make_bid(&sys, BIDDERS[0], TMG_ID, MIN_BID);
+    assert!(replied(
+        &res,
+        BIDDERS[0],
+        Ok(AuctionEvent::BidMade {
+            asset: tmg(TMG_ID),
+            bid: MIN_BID,
+        })
+    ));
+
+    // must fail since the bid exceeds the current one by less than 10%
+    let res = make_bid(&sys, BIDDERS[1], TMG_ID, MIN_BID + MIN_BID / 10 - 1);
+    assert!(replied(&res, BIDDERS[1], Err(AuctionError::WrongBid)));
+
+    assert!(!make_bid(&sys, BIDDERS[1], TMG_ID, MIN_BID + MIN_BID / 10).main_failed());
+    assert_eq!(
+        auction_info(&sys, TMG_ID).min_next_bid(),
+        Some(MIN_BID + MIN_BID / 10 + (MIN_BID + MIN_BID / 10) / 10)
+    );
+}
+
+#[test]
+fn anti_sniping() {
+    let sys = init_system();
+    let anti_sniping = AntiSniping {
+        window: 10,
+        extension: 20,
+    };
+    let kind = AuctionKind::English {
+        minimum_bid: MIN_BID,
+        buy_now_price: None,
+        bid_increment: BidIncrement::Absolute(1),
+        anti_sniping: Some(anti_sniping),
+    };
+    assert!(!start_auction_with_kind(&sys, SELLER, TMG_ID, kind).main_failed());
+    let ended_at = auction_info(&sys, TMG_ID).ended_at;
+
+    // the bid made long before the end doesn't extend the auction
+    let res = make_bid(&sys, BIDDERS[0], TMG_ID, MIN_BID + 1);
+    assert!(replied(
+        &res,
+        BIDDERS[0],
+        Ok(AuctionEvent::BidMade {
+            asset: tmg(TMG_ID),
+            bid: MIN_BID + 1,
+        })
+    ));
+
+    sys.spend_blocks(ended_at - sys.block_height() - anti_sniping.window / 2);
+    assert!(!make_bid(&sys, BIDDERS[1], TMG_ID, MIN_BID + 2).main_failed());
+    let extended_at = auction_info(&sys, TMG_ID).ended_at;
+    assert!(extended_at > ended_at);
+
+    // the original `SettleAuction` message doesn't settle the extended auction
+    sys.spend_blocks(ended_at - sys.block_height() + 1);
+    assert_eq!(auction_info(&sys, TMG_ID).status, Status::InProcess);
+    assert!(!make_bid(&sys, BIDDERS[0], TMG_ID, MIN_BID + 3).main_failed());
+
+    let extended_at = auction_info(&sys, TMG_ID).ended_at;
+    sys.spend_blocks(extended_at - sys.block_height());
+    assert_eq!(auction_info(&sys, TMG_ID).status, Status::ReadyToStart);
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(BIDDERS[0]));
+    assert_eq!(balance_of(&sys, SELLER), MIN_BID + 3);
+}
+
+#[test]
+fn dutch_auction() {
+    let sys = init_system();
+    let kind = AuctionKind::Dutch {
+        starting_price: 10 * MIN_BID,
+        price_step: MIN_BID,
+        floor_price: MIN_BID,
+    };
+    assert!(!start_auction_with_kind(&sys, SELLER, TMG_ID, kind).main_failed());
+    let started_at = auction_info(&sys, TMG_ID).started_at;
+
+    sys.spend_blocks(5);
+    let price = auction_info(&sys, TMG_ID)
+        .dutch_price(sys.block_height())
+        .expect("The auction is Dutch");
+    assert!(price < 10 * MIN_BID);
+    assert_eq!(
+        price,
+        10 * MIN_BID - (sys.block_height() - started_at) as u128 * MIN_BID
+    );
+
+    // must fail since the offered price is too low
+    let res = make_bid(&sys, BIDDERS[0], TMG_ID, MIN_BID);
+    assert!(replied(&res, BIDDERS[0], Err(AuctionError::WrongBid)));
+
+    // the buyer pays the current price even if offers more
+    let res = make_bid(&sys, BIDDERS[1], TMG_ID, 10 * MIN_BID);
+    assert!(replied(
+        &res,
+        BIDDERS[1],
+        Ok(AuctionEvent::AuctionSettled { asset: tmg(TMG_ID) })
+    ));
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(BIDDERS[1]));
+    let paid = balance_of(&sys, SELLER);
+    let sale = auction_info(&sys, TMG_ID)
+        .history
+        .pop()
+        .expect("The auction is settled");
+    // the price of the block the bid has landed in
+    let block = sale.bids.last().expect("The bid is recorded").block;
+    assert_eq!(paid, 10 * MIN_BID - (block - started_at) as u128 * MIN_BID);
+    assert_eq!(sale.final_price, paid);
+    assert_eq!(balance_of(&sys, BIDDERS[1]), BALANCE - paid);
+}
+
+#[test]
+fn sealed_bid_auction() {
+    let sys = init_system();
+    let kind = AuctionKind::SealedBid {
+        minimum_bid: MIN_BID,
+        reveal_duration: DURATION,
+    };
+    assert!(!start_auction_with_kind(&sys, SELLER, TMG_ID, kind).main_failed());
+
+    // must fail since the auction is sealed-bid
+    let res = make_bid(&sys, BIDDERS[0], TMG_ID, MIN_BID + 1);
+    assert!(replied(&res, BIDDERS[0], Err(AuctionError::WrongKind)));
+
+    let res = commit_bid(&sys, BIDDERS[0], TMG_ID, 2 * MIN_BID, 3 * MIN_BID);
+    assert!(replied(
+        &res,
+        BIDDERS[0],
+        Ok(AuctionEvent::BidCommitted { asset: tmg(TMG_ID) })
+    ));
+    assert!(!commit_bid(&sys, BIDDERS[1], TMG_ID, 3 * MIN_BID, 3 * MIN_BID).main_failed());
+    assert!(!commit_bid(&sys, BIDDERS[2], TMG_ID, 4 * MIN_BID, 4 * MIN_BID).main_failed());
+
+    // must fail since the bidding isn't over yet
+    let res = reveal_bid(&sys, BIDDERS[0], TMG_ID, 2 * MIN_BID);
+    assert!(replied(&res, BIDDERS[0], Err(AuctionError::WrongState)));
+
+    sys.spend_blocks(DURATION);
+
+    // must fail since the bid differs from the committed one
+    let res = reveal_bid(&sys, BIDDERS[0], TMG_ID, 2 * MIN_BID + 1);
+    assert!(replied(&res, BIDDERS[0], Err(AuctionError::WrongHash)));
+
+    let res = reveal_bid(&sys, BIDDERS[0], TMG_ID, 2 * MIN_BID);
+    assert!(replied(
+        &res,
+        BIDDERS[0],
+        Ok(AuctionEvent::BidRevealed {
+            asset: tmg(TMG_ID),
+            bid: 2 * MIN_BID,
+        })
+    ));
+    assert_eq!(refund_of(&sys, BIDDERS[0]), MIN_BID);
+    assert!(!reveal_bid(&sys, BIDDERS[1], TMG_ID, 3 * MIN_BID).main_failed());
+    assert_eq!(refund_of(&sys, BIDDERS[0]), 3 * MIN_BID);
+    assert_eq!(refund_of(&sys, BIDDERS[1]), 0);
+
+    // the third bidder doesn't reveal the bid and loses the deposit
+    sys.spend_blocks(DURATION);
+
+    assert_eq!(auction_info(&sys, TMG_ID).status, Status::ReadyToStart);
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(BIDDERS[1]));
+    assert_eq!(balance_of(&sys, SELLER), 3 * MIN_BID + 4 * MIN_BID);
+    assert_eq!(balance_of(&sys, BIDDERS[1]), BALANCE - 3 * MIN_BID);
+    assert_eq!(balance_of(&sys, BIDDERS[2]), BALANCE - 4 * MIN_BID);
+
+    assert!(!claim_refund(&sys, BIDDERS[0]).main_failed());
+    assert_eq!(balance_of(&sys, BIDDERS[0]), BALANCE);
+    assert_eq!(balance_of(&sys, AUCTION_ID), 0);
+}
+
+#[test]
+fn cancel() {
+    let sys = init_system();
+    assert!(!start_auction(&sys, SELLER, TMG_ID).main_failed());
+    assert!(!start_auction(&sys, SELLER, SECOND_TMG_ID).main_failed());
+
+    // must fail since only the seller can cancel the auction
+    let res = cancel_auction(&sys, BIDDERS[0], TMG_ID);
+    assert!(replied(&res, BIDDERS[0], Err(AuctionError::NotOwner)));
+
+    let res = cancel_auction(&sys, SELLER, TMG_ID);
+    assert!(replied(
+        &res,
+        SELLER,
+        Ok(AuctionEvent::AuctionCancelled { asset: tmg(TMG_ID) })
+    ));
+    assert_eq!(auction_info(&sys, TMG_ID).status, Status::ReadyToStart);
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(SELLER));
+
+    // must fail since there is already a bid
+    assert!(!make_bid(&sys, BIDDERS[0], SECOND_TMG_ID, MIN_BID + 1).main_failed());
+    let res = cancel_auction(&sys, SELLER, SECOND_TMG_ID);
+    assert!(replied(&res, SELLER, Err(AuctionError::WrongState)));
+    assert_eq!(tmg_owner(&sys, SECOND_TMG_ID), ActorId::from(AUCTION_ID));
+
+    // the cancelled auction can be started again
+    // and the settlement message of the cancelled one doesn't end it
+    sys.spend_blocks(DURATION / 2);
+    assert!(!start_auction(&sys, SELLER, TMG_ID).main_failed());
+    sys.spend_blocks(DURATION / 2);
+    assert_eq!(auction_info(&sys, TMG_ID).status, Status::InProcess);
+}
+
+#[test]
+fn reserve_price() {
+    let sys = init_system();
+    let kind = AuctionKind::English {
+        minimum_bid: MIN_BID,
+        buy_now_price: None,
+        bid_increment: BidIncrement::Absolute(1),
+        anti_sniping: None,
+    };
+
+    // must fail since the Dutch auction doesn't support the reserve price
+    let dutch = AuctionKind::Dutch {
+        starting_price: 2 * MIN_BID,
+        price_step: 1,
+        floor_price: MIN_BID,
+    };
+    let res = start_auction_with_reserve(&sys, SELLER, TMG_ID, dutch, Some(MIN_BID));
+    assert!(replied(&res, SELLER, Err(AuctionError::WrongParams)));
+
+    let res = start_auction_with_reserve(&sys, SELLER, TMG_ID, kind.clone(), Some(2 * MIN_BID));
+    assert!(!res.main_failed());
+    let res = start_auction_with_reserve(&sys, SELLER, SECOND_TMG_ID, kind, Some(2 * MIN_BID));
+    assert!(!res.main_failed());
+
+    assert!(!make_bid(&sys, BIDDERS[0], TMG_ID, 2 * MIN_BID - 1).main_failed());
+    assert!(!make_bid(&sys, BIDDERS[1], SECOND_TMG_ID, 2 * MIN_BID).main_failed());
+
+    sys.spend_blocks(DURATION);
+
+    // the bid below the reserve price is returned together with the Tamagotchi
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(SELLER));
+    assert_eq!(refund_of(&sys, BIDDERS[0]), 2 * MIN_BID - 1);
+    assert!(!claim_refund(&sys, BIDDERS[0]).main_failed());
+    assert_eq!(balance_of(&sys, BIDDERS[0]), BALANCE);
+
+    assert_eq!(tmg_owner(&sys, SECOND_TMG_ID), ActorId::from(BIDDERS[1]));
+    assert_eq!(balance_of(&sys, SELLER), 2 * MIN_BID);
+    assert_eq!(balance_of(&sys, AUCTION_ID), 0);
+}
+
+#[test]
+fn protocol_fee() {
+    let fee_percent = 10;
+    let sys = init_system_with_fee(fee_percent);
+    assert!(!start_auction(&sys, SELLER, TMG_ID).main_failed());
+    assert!(!make_bid(&sys, BIDDERS[0], TMG_ID, 2 * MIN_BID).main_failed());
+
+    sys.spend_blocks(DURATION);
+
+    let fee = 2 * MIN_BID * fee_percent as u128 / 100;
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(BIDDERS[0]));
+    assert_eq!(balance_of(&sys, FEE_COLLECTOR), fee);
+    assert_eq!(balance_of(&sys, SELLER), 2 * MIN_BID - fee);
+    assert_eq!(balance_of(&sys, BIDDERS[0]), BALANCE - 2 * MIN_BID);
+    assert_eq!(balance_of(&sys, AUCTION_ID), 0);
+}
+
+#[test]
+fn auction_history() {
+    let sys = init_system();
+    assert!(!start_auction(&sys, SELLER, TMG_ID).main_failed());
+    let started_at = auction_info(&sys, TMG_ID).started_at;
+
+    assert!(!make_bid(&sys, BIDDERS[0], TMG_ID, MIN_BID + 1).main_failed());
+    let first_bid_at = sys.block_height();
+    sys.spend_blocks(1);
+    assert!(!make_bid(&sys, BIDDERS[1], TMG_ID, MIN_BID + 2).main_failed());
+
+    let bids = auction_info(&sys, TMG_ID).bids;
+    assert_eq!(bids.len(), 2);
+    assert_eq!(bids[0].bidder, ActorId::from(BIDDERS[0]));
+    assert_eq!(bids[0].amount, MIN_BID + 1);
+    assert_eq!(bids[0].block, first_bid_at);
+    assert!(bids[1].block > bids[0].block);
+    assert!(bids[0].transaction_id.is_some());
+    assert_ne!(bids[0].transaction_id, bids[1].transaction_id);
+
+    sys.spend_blocks(DURATION);
+
+    // the second auction is left without bids
+    assert!(!start_auction(&sys, BIDDERS[1], TMG_ID).main_failed());
+    sys.spend_blocks(DURATION);
+
+    let auction = auction_info(&sys, TMG_ID);
+    assert!(auction.bids.is_empty());
+    assert_eq!(auction.history.len(), 2);
+
+    let sold = &auction.history[0];
+    assert_eq!(sold.seller, ActorId::from(SELLER));
+    assert_eq!(sold.winner, Some(ActorId::from(BIDDERS[1])));
+    assert_eq!(sold.final_price, MIN_BID + 2);
+    assert_eq!(sold.started_at, started_at);
+    assert_eq!(sold.bids, bids);
+
+    let unsold = &auction.history[1];
+    assert_eq!(unsold.seller, ActorId::from(BIDDERS[1]));
+    assert_eq!(unsold.winner, None);
+    assert_eq!(unsold.final_price, 0);
+}
+
+#[test]
+fn reservations_are_made_automatically() {
+    let sys = init_system();
+    assert!(!start_auction(&sys, SELLER, TMG_ID).main_failed());
+    assert!(!make_bid(&sys, BIDDERS[0], TMG_ID, MIN_BID + 1).main_failed());
+
+    let state: AuctionState = sys
+        .get_program(AUCTION_ID)
+        .read_state()
+        .expect("Unable to read the auction state");
+    assert!(!state.reservations.is_empty());
+    for reservation in state.reservations {
+        assert!(reservation.created_at <= sys.block_height());
+        assert!(reservation.expires_at > sys.block_height());
+    }
+}
+
+#[test]
+fn allowlist() {
+    let sys = init_system();
+    let auction = sys.get_program(AUCTION_ID);
+
+    // must fail since only the admin manages the allowlist
+    let res = auction.send(
+        SELLER,
+        AuctionAction::DisallowCollections(vec![TMG_ID.into()]),
+    );
+    assert!(replied(&res, SELLER, Err(AuctionError::NotAdmin)));
+
+    let res = auction.send(
+        ADMIN,
+        AuctionAction::DisallowCollections(vec![TMG_ID.into()]),
+    );
+    assert!(replied(&res, ADMIN, Ok(AuctionEvent::AllowlistUpdated)));
+
+    let res = start_auction(&sys, SELLER, TMG_ID);
+    assert!(replied(&res, SELLER, Err(AuctionError::NotAllowed)));
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(SELLER));
+    assert!(!start_auction(&sys, SELLER, SECOND_TMG_ID).main_failed());
+
+    // the new admin allows the Tamagotchi again
+    let res = auction.send(ADMIN, AuctionAction::SetAdmin(SELLER.into()));
+    assert!(replied(
+        &res,
+        ADMIN,
+        Ok(AuctionEvent::AdminChanged(SELLER.into()))
+    ));
+    let res = auction.send(ADMIN, AuctionAction::AllowCollections(vec![TMG_ID.into()]));
+    assert!(replied(&res, ADMIN, Err(AuctionError::NotAdmin)));
+    let res = auction.send(SELLER, AuctionAction::AllowCollections(vec![TMG_ID.into()]));
+    assert!(replied(&res, SELLER, Ok(AuctionEvent::AllowlistUpdated)));
+
+    assert!(!start_auction(&sys, SELLER, TMG_ID).main_failed());
+    assert_eq!(tmg_owner(&sys, TMG_ID), ActorId::from(AUCTION_ID));
+}
+
+#[test]
+fn nft_auction() {
+    let sys = init_system();
+    let auction = sys.get_program(AUCTION_ID);
+
+    let res = auction.send(
+        SELLER,
+        AuctionAction::StartAuction {
+            asset: nft(TOKEN_ID),
+            duration: DURATION,
+            kind: AuctionKind::default(),
+            reserve_price: None,
+        },
+    );
+    assert!(replied(
+        &res,
+        SELLER,
+        Ok(AuctionEvent::AuctionStarted {
+            asset: nft(TOKEN_ID)
+        })
+    ));
+    assert_eq!(nft_owner(&sys, TOKEN_ID), ActorId::from(AUCTION_ID));
+
+    let res = auction.send(
+        BIDDERS[0],
+        AuctionAction::MakeBid {
+            asset: nft(TOKEN_ID),
+            bid: MIN_BID,
+        },
+    );
+    assert!(!res.main_failed());
+
+    sys.spend_blocks(DURATION);
+
+    assert_eq!(nft_owner(&sys, TOKEN_ID), ActorId::from(BIDDERS[0]));
+    assert_eq!(balance_of(&sys, SELLER), MIN_BID);
+}
+
+#[test]
+fn start_auction_of_asset_held_by_auction() {
+    let sys = init_system();
+
+    // must fail since only the rerun of a pending start finds the asset on the auction
+    let res = sys.get_program(AUCTION_ID).send(
+        SELLER,
+        AuctionAction::StartAuction {
+            asset: nft(GIFTED_TOKEN_ID),
+            duration: DURATION,
+            kind: AuctionKind::default(),
+            reserve_price: None,
+        },
+    );
+    assert!(replied(&res, SELLER, Err(AuctionError::NotOwner)));
+    assert_eq!(nft_owner(&sys, GIFTED_TOKEN_ID), ActorId::from(AUCTION_ID));
+    let state: AuctionState = sys
+        .get_program(AUCTION_ID)
+        .read_state()
+        .expect("Unable to read the auction state");
+    assert!(state.auction(&nft(GIFTED_TOKEN_ID)).is_none());
+}
+
+#[test]
+fn attribute_auction() {
+    let sys = init_system();
+    let auction = sys.get_program(AUCTION_ID);
+    let start = |holder: u64| AuctionAction::StartAuction {
+        asset: attribute(ATTRIBUTE_ID, holder),
+        duration: DURATION,
+        kind: AuctionKind::default(),
+        reserve_price: None,
+    };
+
+    // only the holder of the attribute can sell it
+    let res = auction.send(BIDDERS[1], start(SELLER));
+    assert!(replied(&res, BIDDERS[1], Err(AuctionError::NotOwner)));
+    let res = auction.send(BIDDERS[1], start(BIDDERS[1]));
+    assert!(replied(&res, BIDDERS[1], Err(AuctionError::NotOwner)));
+
+    // must fail since the attribute isn't approved to the auction
+    let res = auction.send(SELLER, start(SELLER));
+    assert!(replied(
+        &res,
+        SELLER,
+        Err(AuctionError::UnableToChangeOwner)
+    ));
+
+    // two holders of the same attribute sell it at the same time
+    for holder in [SELLER, BIDDERS[2]] {
+        assert!(!approve_attribute(&sys, holder, ATTRIBUTE_ID).main_failed());
+        let res = auction.send(holder, start(holder));
+        assert!(replied(
+            &res,
+            holder,
+            Ok(AuctionEvent::AuctionStarted {
+                asset: attribute(ATTRIBUTE_ID, holder)
+            })
+        ));
+        // the attribute stays with the holder until the settlement
+        assert!(attributes_of(&sys, holder).contains(&ATTRIBUTE_ID));
+    }
+
+    for (bidder, holder) in [(BIDDERS[0], SELLER), (BIDDERS[1], BIDDERS[2])] {
+        let res = auction.send(
+            bidder,
+            AuctionAction::MakeBid {
+                asset: attribute(ATTRIBUTE_ID, holder),
+                bid: MIN_BID,
+            },
+        );
+        assert!(!res.main_failed());
+    }
+
+    sys.spend_blocks(DURATION);
+
+    for (bidder, holder) in [(BIDDERS[0], SELLER), (BIDDERS[1], BIDDERS[2])] {
+        assert!(!attributes_of(&sys, holder).contains(&ATTRIBUTE_ID));
+        assert!(attributes_of(&sys, bidder).contains(&ATTRIBUTE_ID));
+        assert_eq!(approval_of(&sys, holder, ATTRIBUTE_ID), None);
+    }
+    assert_eq!(balance_of(&sys, SELLER), MIN_BID);
+    assert_eq!(balance_of(&sys, BIDDERS[2]), BALANCE + MIN_BID);
+}
+
+#[test]
+fn cancelled_attribute_auction() {
+    let sys = init_system();
+    assert!(!approve_attribute(&sys, SELLER, ATTRIBUTE_ID).main_failed());
+    let res = sys.get_program(AUCTION_ID).send(
+        SELLER,
+        AuctionAction::StartAuction {
+            asset: attribute(ATTRIBUTE_ID, SELLER),
+            duration: DURATION,
+            kind: AuctionKind::default(),
+            reserve_price: None,
+        },
+    );
+    assert!(!res.main_failed());
+
+    let res = sys.get_program(AUCTION_ID).send(
+        SELLER,
+        AuctionAction::CancelAuction {
+            asset: attribute(ATTRIBUTE_ID, SELLER),
+        },
+    );
+    assert!(replied(
+        &res,
+        SELLER,
+        Ok(AuctionEvent::AuctionCancelled {
+            asset: attribute(ATTRIBUTE_ID, SELLER)
+        })
+    ));
+    // the approval is released leaving the attribute to the holder
+    assert_eq!(approval_of(&sys, SELLER, ATTRIBUTE_ID), None);
+    assert!(attributes_of(&sys, SELLER).contains(&ATTRIBUTE_ID));
+}
@@ -0,0 +1,568 @@
+#![allow(dead_code)]
+
+use auction_io::*;
+use ft_main_io::{FTokenAction, FTokenEvent, LogicAction};
+use gstd::ActorId;
+use gtest::{Log, Program, RunResult, System, WasmProgram};
+use nft_io::{NFTAction, NFTEvent, NFTTransfer, TokenId};
+use parity_scale_codec::{Decode, Encode};
+use std::collections::{BTreeMap, BTreeSet};
+use store_io::{StoreAction, StoreEvent};
+use tmg_io::{TmgAction, TmgEvent};
+
+pub const AUCTION_ID: u64 = 1;
+pub const FT_ID: u64 = 2;
+pub const TMG_ID: u64 = 3;
+pub const SECOND_TMG_ID: u64 = 4;
+pub const NFT_ID: u64 = 5;
+pub const STORE_ID: u64 = 6;
+pub const TOKEN_ID: u64 = 0;
+/// The token sent to the auction by mistake.
+pub const GIFTED_TOKEN_ID: u64 = 1;
+pub const ATTRIBUTE_ID: AttributeId = 0;
+pub const ADMIN: u64 = 98;
+pub const FEE_COLLECTOR: u64 = 99;
+pub const SELLER: u64 = 100;
+pub const BIDDERS: [u64; 3] = [101, 102, 103];
+/// The bidder whose first token transfer isn't answered.
+pub const SLOW_BIDDER: u64 = 104;
+pub const BALANCE: u128 = 1_000_000;
+pub const MIN_BID: Bid = 1_000;
+pub const DURATION: Duration = 100;
+
+/// Tamagotchi that only knows its owner.
+#[derive(Debug)]
+pub struct TamagotchiMock {
+    owner: ActorId,
+}
+
+impl WasmProgram for TamagotchiMock {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let action =
+            TmgAction::decode(&mut &payload[..]).map_err(|_| "Unable to decode `TmgAction`")?;
+        match action {
+            TmgAction::Owner => Ok(Some(TmgEvent::Owner(self.owner).encode())),
+            TmgAction::Transfer(new_owner) => {
+                self.owner = new_owner;
+                Ok(Some(TmgEvent::Transfer(new_owner).encode()))
+            }
+            _ => Err("Unexpected `TmgAction`"),
+        }
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Ok(self.owner.encode())
+    }
+}
+
+/// Non-fungible token that executes each transfer only once.
+#[derive(Debug, Default)]
+pub struct NftMock {
+    owners: BTreeMap<TokenId, ActorId>,
+    transactions: BTreeMap<u64, NFTEvent>,
+}
+
+impl WasmProgram for NftMock {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let action =
+            NFTAction::decode(&mut &payload[..]).map_err(|_| "Unable to decode `NFTAction`")?;
+        let event = match action {
+            NFTAction::Owner { token_id } => NFTEvent::Owner {
+                owner: self.owners.get(&token_id).copied().unwrap_or_default(),
+                token_id,
+            },
+            NFTAction::Transfer {
+                transaction_id,
+                to,
+                token_id,
+            } => {
+                if let Some(event) = self.transactions.get(&transaction_id) {
+                    return Ok(Some(event.encode()));
+                }
+                let from = self.owners.insert(token_id, to).ok_or("No token")?;
+                let event = NFTEvent::Transfer(NFTTransfer { from, to, token_id });
+                self.transactions.insert(transaction_id, event.clone());
+                event
+            }
+            _ => return Err("Unexpected `NFTAction`"),
+        };
+        Ok(Some(event.encode()))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Ok(self.owners.encode())
+    }
+}
+
+/// Tamagotchi store that only keeps the attributes of accounts and their approvals
+/// without checking who approves them.
+/// Like the store, it replies success to a transfer or a release that has already been made.
+#[derive(Debug, Default)]
+pub struct StoreMock {
+    owners: BTreeMap<ActorId, BTreeSet<AttributeId>>,
+    approvals: BTreeMap<(ActorId, AttributeId), ActorId>,
+}
+
+impl WasmProgram for StoreMock {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let action =
+            StoreAction::decode(&mut &payload[..]).map_err(|_| "Unable to decode `StoreAction`")?;
+        let event = match action {
+            StoreAction::GetAttributes { tamagotchi_id } => StoreEvent::Attributes {
+                attributes: self.owners.get(&tamagotchi_id).cloned().unwrap_or_default(),
+            },
+            StoreAction::GetApproval {
+                holder,
+                attribute_id,
+            } => StoreEvent::Approval {
+                spender: self.approvals.get(&(holder, attribute_id)).copied(),
+            },
+            StoreAction::ApproveAttribute {
+                holder,
+                attribute_id,
+                spender,
+            } => {
+                if self.approvals.contains_key(&(holder, attribute_id)) {
+                    return Err("The attribute is already approved");
+                }
+                self.approvals.insert((holder, attribute_id), spender);
+                StoreEvent::AttributeApproved {
+                    holder,
+                    attribute_id,
+                    spender,
+                }
+            }
+            StoreAction::TransferAttribute {
+                from,
+                to,
+                attribute_id,
+            } => {
+                if self
+                    .owners
+                    .get_mut(&from)
+                    .map_or(false, |attributes| attributes.remove(&attribute_id))
+                {
+                    self.approvals.remove(&(from, attribute_id));
+                    self.owners.entry(to).or_default().insert(attribute_id);
+                } else if !self
+                    .owners
+                    .get(&to)
+                    .map_or(false, |attributes| attributes.contains(&attribute_id))
+                {
+                    return Err("The sender doesn't own that attribute");
+                }
+                StoreEvent::AttributeTransferred {
+                    from,
+                    to,
+                    attribute_id,
+                }
+            }
+            StoreAction::ReleaseAttribute {
+                holder,
+                attribute_id,
+            } => {
+                self.approvals.remove(&(holder, attribute_id));
+                StoreEvent::AttributeReleased {
+                    holder,
+                    attribute_id,
+                }
+            }
+            _ => return Err("Unexpected `StoreAction`"),
+        };
+        Ok(Some(event.encode()))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Ok((&self.owners, &self.approvals).encode())
+    }
+}
+
+/// Fungible token that executes each transaction only once like the sharded fungible token does.
+/// The first transfer from each of `slow_senders` is neither executed nor answered
+/// as if the reply has been lost.
+#[derive(Debug, Default)]
+pub struct FungibleTokenMock {
+    balances: BTreeMap<ActorId, u128>,
+    transactions: BTreeMap<u64, bool>,
+    slow_senders: BTreeSet<ActorId>,
+}
+
+impl FungibleTokenMock {
+    fn transfer(&mut self, sender: ActorId, recipient: ActorId, amount: u128) -> bool {
+        let sender_balance = self.balances.entry(sender).or_default();
+        if *sender_balance < amount {
+            return false;
+        }
+        *sender_balance -= amount;
+        *self.balances.entry(recipient).or_default() += amount;
+        true
+    }
+}
+
+impl WasmProgram for FungibleTokenMock {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let action = FTokenAction::decode(&mut &payload[..])
+            .map_err(|_| "Unable to decode `FTokenAction`")?;
+        let event = match action {
+            FTokenAction::Message {
+                transaction_id,
+                payload:
+                    LogicAction::Transfer {
+                        sender,
+                        recipient,
+                        amount,
+                    },
+            } => {
+                if self.slow_senders.remove(&sender) {
+                    return Ok(None);
+                }
+                let success = if let Some(success) = self.transactions.get(&transaction_id) {
+                    *success
+                } else {
+                    let success = self.transfer(sender, recipient, amount);
+                    self.transactions.insert(transaction_id, success);
+                    success
+                };
+                if success {
+                    FTokenEvent::Ok
+                } else {
+                    FTokenEvent::Err
+                }
+            }
+            FTokenAction::GetBalance(account) => {
+                FTokenEvent::Balance(self.balances.get(&account).copied().unwrap_or_default())
+            }
+            _ => return Err("Unexpected `FTokenAction`"),
+        };
+        Ok(Some(event.encode()))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Ok(self.balances.encode())
+    }
+}
+
+pub fn init_system() -> System {
+    init_system_with_fee(0)
+}
+
+pub fn init_system_with_fee(fee_percent: u8) -> System {
+    let sys = System::new();
+    sys.init_logger();
+
+    let auction = Program::current(&sys);
+    let res = auction.send(
+        ADMIN,
+        InitAuction {
+            ft_contract_id: FT_ID.into(),
+            fee_collector: FEE_COLLECTOR.into(),
+            fee_percent,
+        },
+    );
+    assert!(!res.main_failed());
+    let res = auction.send(
+        ADMIN,
+        AuctionAction::AllowCollections(vec![
+            TMG_ID.into(),
+            SECOND_TMG_ID.into(),
+            NFT_ID.into(),
+            STORE_ID.into(),
+        ]),
+    );
+    assert!(!res.main_failed());
+
+    let ft = FungibleTokenMock {
+        balances: BIDDERS
+            .iter()
+            .chain([&SLOW_BIDDER])
+            .map(|bidder| (ActorId::from(*bidder), BALANCE))
+            .collect(),
+        slow_senders: [ActorId::from(SLOW_BIDDER)].into(),
+        ..Default::default()
+    };
+    Program::mock_with_id(&sys, FT_ID, ft);
+    for tmg_id in [TMG_ID, SECOND_TMG_ID] {
+        Program::mock_with_id(
+            &sys,
+            tmg_id,
+            TamagotchiMock {
+                owner: SELLER.into(),
+            },
+        );
+    }
+
+    Program::mock_with_id(
+        &sys,
+        NFT_ID,
+        NftMock {
+            owners: [
+                (TokenId::from(TOKEN_ID), ActorId::from(SELLER)),
+                (TokenId::from(GIFTED_TOKEN_ID), ActorId::from(AUCTION_ID)),
+            ]
+            .into(),
+            ..Default::default()
+        },
+    );
+
+    Program::mock_with_id(
+        &sys,
+        STORE_ID,
+        StoreMock {
+            owners: [SELLER, BIDDERS[2]]
+                .into_iter()
+                .map(|holder| (ActorId::from(holder), [ATTRIBUTE_ID].into()))
+                .collect(),
+            ..Default::default()
+        },
+    );
+
+    sys
+}
+
+pub fn tmg(tmg_id: u64) -> Asset {
+    Asset::Tamagotchi(tmg_id.into())
+}
+
+pub fn nft(token_id: u64) -> Asset {
+    Asset::Nft {
+        contract_id: NFT_ID.into(),
+        token_id: token_id.into(),
+    }
+}
+
+pub fn attribute(attribute_id: AttributeId, holder: u64) -> Asset {
+    Asset::Attribute {
+        store_id: STORE_ID.into(),
+        attribute_id,
+        holder: holder.into(),
+    }
+}
+
+pub fn start_auction(sys: &System, seller: u64, tmg_id: u64) -> RunResult {
+    start_auction_with_kind(
+        sys,
+        seller,
+        tmg_id,
+        AuctionKind::English {
+            minimum_bid: MIN_BID,
+            buy_now_price: None,
+            bid_increment: BidIncrement::Absolute(1),
+            anti_sniping: None,
+        },
+    )
+}
+
+pub fn start_auction_with_kind(
+    sys: &System,
+    seller: u64,
+    tmg_id: u64,
+    kind: AuctionKind,
+) -> RunResult {
+    start_auction_with_reserve(sys, seller, tmg_id, kind, None)
+}
+
+pub fn start_auction_with_reserve(
+    sys: &System,
+    seller: u64,
+    tmg_id: u64,
+    kind: AuctionKind,
+    reserve_price: Option<Bid>,
+) -> RunResult {
+    sys.get_program(AUCTION_ID).send(
+        seller,
+        AuctionAction::StartAuction {
+            asset: tmg(tmg_id),
+            duration: DURATION,
+            kind,
+            reserve_price,
+        },
+    )
+}
+
+pub fn cancel_auction(sys: &System, account: u64, tmg_id: u64) -> RunResult {
+    sys.get_program(AUCTION_ID)
+        .send(account, AuctionAction::CancelAuction { asset: tmg(tmg_id) })
+}
+
+pub fn make_bid(sys: &System, bidder: u64, tmg_id: u64, bid: Bid) -> RunResult {
+    sys.get_program(AUCTION_ID).send(
+        bidder,
+        AuctionAction::MakeBid {
+            asset: tmg(tmg_id),
+            bid,
+        },
+    )
+}
+
+pub fn commit_bid(sys: &System, bidder: u64, tmg_id: u64, bid: Bid, deposit: Bid) -> RunResult {
+    sys.get_program(AUCTION_ID).send(
+        bidder,
+        AuctionAction::CommitBid {
+            asset: tmg(tmg_id),
+            hash: bid_hash(bid, &salt(bidder)),
+            deposit,
+        },
+    )
+}
+
+pub fn reveal_bid(sys: &System, bidder: u64, tmg_id: u64, bid: Bid) -> RunResult {
+    sys.get_program(AUCTION_ID).send(
+        bidder,
+        AuctionAction::RevealBid {
+            asset: tmg(tmg_id),
+            bid,
+            salt: salt(bidder),
+        },
+    )
+}
+
+pub fn salt(account: u64) -> [u8; 32] {
+    [account as u8; 32]
+}
+
+pub fn settle_auction(sys: &System, account: u64, tmg_id: u64) -> RunResult {
+    sys.get_program(AUCTION_ID)
+        .send(account, AuctionAction::SettleAuction { asset: tmg(tmg_id) })
+}
+
+pub fn claim_refund(sys: &System, account: u64) -> RunResult {
+    sys.get_program(AUCTION_ID)
+        .send(account, AuctionAction::ClaimRefund)
+}
+
+pub fn refund_of(sys: &System, account: u64) -> Bid {
+    let state: AuctionState = sys
+        .get_program(AUCTION_ID)
+        .read_state()
+        .expect("Unable to read the auction state");
+    state.refund_of(&account.into())
+}
+
+pub fn auction_info(sys: &System, tmg_id: u64) -> AuctionInfo {
+    let state: AuctionState = sys
+        .get_program(AUCTION_ID)
+        .read_state()
+        .expect("Unable to read the auction state");
+    state
+        .auction(&tmg(tmg_id))
+        .cloned()
+        .expect("The auction doesn't exist")
+}
+
+pub fn has_auction(sys: &System, tmg_id: u64) -> bool {
+    let state: AuctionState = sys
+        .get_program(AUCTION_ID)
+        .read_state()
+        .expect("Unable to read the auction state");
+    state.auction(&tmg(tmg_id)).is_some()
+}
+
+pub fn tmg_owner(sys: &System, tmg_id: u64) -> ActorId {
+    sys.get_program(tmg_id)
+        .read_state()
+        .expect("Unable to read the Tamagotchi state")
+}
+
+pub fn nft_owner(sys: &System, token_id: u64) -> ActorId {
+    let owners: BTreeMap<TokenId, ActorId> = sys
+        .get_program(NFT_ID)
+        .read_state()
+        .expect("Unable to read the NFT state");
+    owners
+        .get(&token_id.into())
+        .copied()
+        .expect("The token doesn't exist")
+}
+
+type StoreState = (
+    BTreeMap<ActorId, BTreeSet<AttributeId>>,
+    BTreeMap<(ActorId, AttributeId), ActorId>,
+);
+
+pub fn attributes_of(sys: &System, account: u64) -> BTreeSet<AttributeId> {
+    let (owners, _): StoreState = sys
+        .get_program(STORE_ID)
+        .read_state()
+        .expect("Unable to read the store state");
+    owners.get(&account.into()).cloned().unwrap_or_default()
+}
+
+pub fn approval_of(sys: &System, holder: u64, attribute_id: AttributeId) -> Option<ActorId> {
+    let (_, approvals): StoreState = sys
+        .get_program(STORE_ID)
+        .read_state()
+        .expect("Unable to read the store state");
+    approvals.get(&(holder.into(), attribute_id)).copied()
+}
+
+pub fn approve_attribute(sys: &System, holder: u64, attribute_id: AttributeId) -> RunResult {
+    sys.get_program(STORE_ID).send(
+        holder,
+        StoreAction::ApproveAttribute {
+            holder: holder.into(),
+            attribute_id,
+            spender: AUCTION_ID.into(),
+        },
+    )
+}
+
+pub fn balance_of(sys: &System, account: u64) -> u128 {
+    let balances: BTreeMap<ActorId, u128> = sys
+        .get_program(FT_ID)
+        .read_state()
+        .expect("Unable to read the fungible token state");
+    balances.get(&account.into()).copied().unwrap_or_default()
+}
+
+/// Checks that the auction replied to the account with the given result.
+pub fn replied(res: &RunResult, account: u64, reply: Result<AuctionEvent, AuctionError>) -> bool {
+    res.contains(&Log::builder().dest(account).payload(reply))
+}
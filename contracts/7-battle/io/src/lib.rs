@@ -2,12 +2,12 @@
 
 use gmeta::{In, InOut, Metadata};
 use gstd::{prelude::*, ActorId};
-use store_io::{AttributeId, TamagotchiId};
+use store_io::{AttributeId, Effects, TamagotchiId, TransactionId};
 
 pub struct BattleMetadata;
 
 impl Metadata for BattleMetadata {
-    type Init = In<ActorId>;
+    type Init = In<InitBattle>;
     type Handle = InOut<BattleAction, BattleEvent>;
     type Others = ();
     type Reply = ();
@@ -15,14 +15,183 @@ impl Metadata for BattleMetadata {
     type State = Battle;
 }
 
+#[derive(Default, Encode, Decode, TypeInfo)]
+pub struct InitBattle {
+    pub tmg_store_id: ActorId,
+    pub ft_contract_id: ActorId,
+    /// The tokens each player pays to register (`0` for battles without stakes).
+    pub entry_fee: u128,
+    /// The account receiving the arena fee.
+    pub fee_collector: ActorId,
+    /// The percent of the prize pool taken as the arena fee when the game is over.
+    pub arena_fee_percent: u8,
+    pub config: Config,
+}
+
+/// The parameters of the arena that the admin can adjust.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Config {
+    /// The gas of the delayed messages the program sends to itself.
+    pub gas_amount: u64,
+    /// The delay (in blocks) of the update of the players' attributes between the rounds of steps.
+    pub time_for_update: u32,
+    /// The number of moves after which the players wait for the update.
+    pub max_steps_for_round: u8,
+    /// The power is generated in `min_power..max_power`,
+    /// and `max_power` minus the power is the energy at the start of a match.
+    pub max_power: u16,
+    pub min_power: u16,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            gas_amount: 10_000_000_000,
+            time_for_update: 6,
+            max_steps_for_round: 5,
+            max_power: 10_000,
+            min_power: 3_000,
+        }
+    }
+}
+
+pub type RoomId = u64;
+
 #[derive(Default, Encode, Decode, TypeInfo)]
 pub struct Battle {
+    /// Can cancel or reset the game in any room and configure the arena.
+    pub admin: ActorId,
+    pub config: Config,
+    /// No rooms can be created and no games can be started while the arena is paused.
+    pub paused: bool,
+    pub tmg_store_id: ActorId,
+    pub ft_contract_id: ActorId,
+    pub entry_fee: u128,
+    pub fee_collector: ActorId,
+    pub arena_fee_percent: u8,
+    pub transaction_id: TransactionId,
+    /// Pending transfers of the entry fees and refunds.
+    pub transactions: BTreeMap<TamagotchiId, TransactionId>,
+    /// The stats of all Tamagotchis that have ever played. They are kept between the games.
+    pub stats: BTreeMap<TamagotchiId, PlayerStats>,
+    pub rooms: BTreeMap<RoomId, Room>,
+    /// The ID of the next created room.
+    pub next_room_id: RoomId,
+    /// The entry fees to be claimed back with `ClaimRefund`.
+    pub refunds: BTreeMap<ActorId, u128>,
+    /// The transactions and amounts of the refunds being claimed.
+    pub refund_claims: BTreeMap<ActorId, (TransactionId, u128)>,
+}
+
+impl Battle {
+    /// Returns the `n` Tamagotchis with the highest ratings.
+    pub fn leaderboard(&self, n: usize) -> Vec<(TamagotchiId, PlayerStats)> {
+        let mut leaderboard: Vec<(TamagotchiId, PlayerStats)> = self
+            .stats
+            .iter()
+            .map(|(tmg_id, stats)| (*tmg_id, stats.clone()))
+            .collect();
+        leaderboard.sort_by(|(_, a), (_, b)| b.rating.cmp(&a.rating));
+        leaderboard.truncate(n);
+        leaderboard
+    }
+
+    /// Returns the stats of the Tamagotchi (`None` if it has never played).
+    pub fn stats_of(&self, tmg_id: &TamagotchiId) -> Option<PlayerStats> {
+        self.stats.get(tmg_id).cloned()
+    }
+}
+
+/// The room where a game is played independently from the games in other rooms.
+#[derive(Default, Encode, Decode, TypeInfo)]
+pub struct Room {
+    pub id: RoomId,
+    pub creator: ActorId,
+    /// The accounts that can register in a private room besides its creator
+    /// (`None` for a public room).
+    pub invited: Option<BTreeSet<ActorId>>,
+    /// All Tamagotchis registered for the tournament.
     pub players: Vec<Player>,
     pub state: BattleState,
+    /// The fighter of the current match who makes the move (`0` or `1`).
     pub current_turn: u8,
+    /// The champion of the tournament.
     pub winner: ActorId,
     pub steps: u8,
-    pub tmg_store_id: ActorId,
+    /// The tournament bracket: the matches of each round played so far.
+    pub rounds: Vec<Vec<Match>>,
+    /// The index of the match being fought in the last round.
+    pub current_match: u32,
+    pub random: RandomState,
+    /// The moves made in the current game.
+    pub moves: Vec<MoveRecord>,
+    /// Increased on each turn to tell the timeout of the current turn from the previous ones.
+    pub turn_number: u64,
+    /// The block until which the player must make the move.
+    pub turn_deadline: u32,
+    /// The entry fees held by the program until the prize is paid out to the winner.
+    pub prize_pool: u128,
+    /// The arena fee of the finished game that isn't transferred yet.
+    pub arena_fee: u128,
+    /// The entry fees paid for the registered Tamagotchis,
+    /// including the ones dropped for unrevealed seeds until the game is over.
+    pub stakes: BTreeMap<TamagotchiId, Stake>,
+    /// The transactions of the arena fee and the prize (the prize uses the next ID).
+    pub payout_transaction_id: Option<TransactionId>,
+}
+
+impl Room {
+    pub fn current_match(&self) -> Option<&Match> {
+        self.rounds.last()?.get(self.current_match as usize)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct PlayerStats {
+    /// The number of the fought matches won (byes aren't counted).
+    pub wins: u32,
+    pub losses: u32,
+    /// The number of the tournaments played.
+    pub games: u32,
+    /// The ELO rating.
+    pub rating: u32,
+}
+
+#[derive(Default, Clone, Encode, Decode, TypeInfo)]
+pub struct RandomState {
+    /// Increased on each random number so that the numbers differ within a message.
+    pub nonce: u64,
+    /// Mixed from the seeds revealed by the players.
+    pub seed: [u8; 32],
+    /// Hashes of the seeds committed by the players (`blake2b_256(seed)`).
+    pub commitments: BTreeMap<ActorId, [u8; 32]>,
+    /// The block after which the players who haven't revealed their seeds
+    /// can be dropped from the tournament.
+    pub reveal_deadline: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Stake {
+    /// The account that has paid the entry fee and receives the refund.
+    pub payer: ActorId,
+    pub amount: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Match {
+    /// The second Tamagotchi is `None` if the first one has a bye.
+    pub tamagotchis: (TamagotchiId, Option<TamagotchiId>),
+    pub winner: Option<TamagotchiId>,
+}
+
+impl Match {
+    pub fn new(first: TamagotchiId, second: Option<TamagotchiId>) -> Self {
+        Self {
+            tamagotchis: (first, second),
+            // the Tamagotchi with a bye advances to the next round without a fight
+            winner: if second.is_none() { Some(first) } else { None },
+        }
+    }
 }
 
 #[derive(Default, Clone, Encode, Decode, TypeInfo)]
@@ -30,8 +199,49 @@ pub struct Player {
     pub owner: ActorId,
     pub tmg_id: TamagotchiId,
     pub energy: u16,
+    /// Generated when the tournament starts
+    /// so that it depends on the seeds revealed during the registration.
     pub power: u16,
+    /// The level of the Tamagotchi at the registration. It increases the power.
+    pub level: u32,
+    /// Equipped attributes of the Tamagotchi.
     pub attributes: BTreeSet<AttributeId>,
+    /// Combined effects of the equipped attributes.
+    pub effects: Effects,
+    /// The number of the player's moves after which the move can be made again.
+    pub cooldowns: BTreeMap<Move, u8>,
+    /// The damage of the opponent's next attack is halved.
+    pub defending: bool,
+    /// The number of turns missed in the current match.
+    pub timeouts: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct MoveRecord {
+    /// The round of the tournament bracket.
+    pub round: u32,
+    /// The index of the match in the round.
+    pub match_index: u32,
+    /// The step in the current round of steps of the match.
+    pub step: u8,
+    pub attacker: TamagotchiId,
+    pub defender: TamagotchiId,
+    pub kind: Move,
+    pub damage: u16,
+    pub critical: bool,
+    pub attacker_energy: u16,
+    pub defender_energy: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Encode, Decode, TypeInfo)]
+pub enum Move {
+    Attack,
+    /// Halves the damage of the opponent's next attack.
+    Defend,
+    /// Restores a part of the energy (not above the energy at the start of the match).
+    Heal,
+    /// A stronger attack that costs the attacker a part of its energy.
+    Special,
 }
 
 #[derive(Default, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
@@ -41,22 +251,150 @@ pub enum BattleState {
     Moves,
     Waiting,
     GameIsOver,
+    /// The entry fees are being refunded after the game is cancelled or reset.
+    /// The room takes no registrations until all of them are refunded.
+    Refunding,
 }
 
 #[derive(Encode, Decode, TypeInfo, Debug)]
 pub enum BattleAction {
-    Register { tmg_id: TamagotchiId },
-    MakeMove,
-    UpdateInfo,
-    StartNewGame,
+    /// Creates a room. The room is private if the invite list is given.
+    CreateRoom {
+        invited: Option<BTreeSet<ActorId>>,
+    },
+    /// Adds the accounts to the invite list of a private room. Only the room creator can invite.
+    Invite {
+        room_id: RoomId,
+        players: BTreeSet<ActorId>,
+    },
+    Register {
+        room_id: RoomId,
+        tmg_id: TamagotchiId,
+    },
+    /// Removes the room whose game is over and whose prize is paid out
+    /// or the room nobody has registered in and that holds no entry fees.
+    /// Only the admin or the room creator can close the room.
+    CloseRoom {
+        room_id: RoomId,
+    },
+    /// Closes the registration and generates the bracket.
+    /// It can't start until all committed seeds are revealed. Once the reveal deadline passes,
+    /// the players who haven't revealed their seeds are dropped and their entry fees stay in the prize pool
+    /// (they are refunded if the game is reset).
+    /// The tournament also starts automatically once the maximum number of players registers
+    /// and all committed seeds are revealed.
+    StartTournament {
+        room_id: RoomId,
+    },
+    /// Commits `blake2b_256(seed)` of a seed that is mixed into the battle randomness.
+    /// Only registered players can commit seeds during the registration.
+    /// Each commitment moves the reveal deadline.
+    CommitSeed {
+        room_id: RoomId,
+        hash: [u8; 32],
+    },
+    /// Reveals the committed seed. Seeds can be revealed until the tournament starts.
+    RevealSeed {
+        room_id: RoomId,
+        seed: [u8; 32],
+    },
+    MakeMove {
+        room_id: RoomId,
+        kind: Move,
+    },
+    /// Gives up the current match. Can be sent by any of the fighters.
+    Surrender {
+        room_id: RoomId,
+    },
+    /// Skips the turn or forfeits the match of the player who hasn't made the move in time.
+    /// Sent by the program to itself when the turn begins.
+    /// The opponent can also send it once `turn_deadline` of the room is reached,
+    /// if the program has lacked gas for the check.
+    TurnTimeout {
+        room_id: RoomId,
+        turn_number: u64,
+    },
+    UpdateInfo {
+        room_id: RoomId,
+    },
+    StartNewGame {
+        room_id: RoomId,
+    },
+    /// Retries the payout of the prize if it has failed when the game was over.
+    PayPrize {
+        room_id: RoomId,
+    },
+    /// Refunds the entry fees and clears the registration.
+    /// Only the admin or the room creator can cancel the game.
+    /// Cancelling the game again retries the refunds that have failed.
+    CancelGame {
+        room_id: RoomId,
+    },
+    UpdateConfig(Config),
+    SetTmgStore {
+        tmg_store_id: ActorId,
+    },
+    /// Stops the creation of rooms, the registration and the start of games.
+    /// The games being played can be finished.
+    Pause,
+    Unpause,
+    /// Stops the game in any state, refunds the entry fees and clears the registration.
+    ResetGame {
+        room_id: RoomId,
+    },
+    /// Transfers the entry fees that couldn't be refunded to the sender.
+    ClaimRefund,
 }
 
 #[derive(Encode, Decode, TypeInfo)]
 pub enum BattleEvent {
-    Registered { tmg_id: TamagotchiId },
-    MoveMade,
-    GoToWaitingState,
-    GameIsOver,
+    RoomCreated {
+        room_id: RoomId,
+    },
+    Invited,
+    Registered {
+        tmg_id: TamagotchiId,
+    },
+    RoomClosed {
+        room_id: RoomId,
+    },
+    TournamentStarted,
+    SeedCommitted,
+    SeedRevealed,
+    MoveMade(MoveRecord),
+    /// The move ends the round of steps
+    /// and the players wait for the update of their attributes.
+    GoToWaitingState(MoveRecord),
+    TurnSkipped {
+        tmg_id: TamagotchiId,
+    },
+    /// `last_move` is `None` if the loser has surrendered or forfeited the match.
+    MatchIsOver {
+        last_move: Option<MoveRecord>,
+        winner: TamagotchiId,
+    },
+    GameIsOver {
+        last_move: Option<MoveRecord>,
+        winner: TamagotchiId,
+    },
     InfoUpdated,
     NewGame,
+    PrizePaid {
+        winner: ActorId,
+        amount: u128,
+    },
+    GameCancelled,
+    ConfigUpdated,
+    TmgStoreSet,
+    Paused,
+    Unpaused,
+    GameReset,
+    /// The Tamagotchi has been registered by another message while the entry fee was being paid,
+    /// and the fee couldn't be refunded. It can be claimed with `ClaimRefund`.
+    RefundPending {
+        amount: u128,
+    },
+    RefundClaimed {
+        amount: u128,
+    },
 }
@@ -1,141 +1,921 @@
 #![no_std]
 
-use battle_io::{BattleAction, BattleEvent, BattleState, Player};
-use core::array;
+use battle_io::{
+    BattleAction, BattleEvent, BattleState, Config, InitBattle, Match, Move, MoveRecord, Player,
+    PlayerStats, RandomState, RoomId, Stake,
+};
+use ft_main_io::{FTokenAction, FTokenEvent, LogicAction};
 use gstd::{exec, msg, prelude::*, ActorId};
 use store_io::*;
 use tmg_io::{TmgAction, TmgEvent};
-pub const SWORD_ID: AttributeId = 1;
-pub const SWORD_POWER: u16 = 5;
-const GAS_AMOUNT: u64 = 10_000_000_000;
-const TIME_FOR_UPDATE: u32 = 6;
-const MAX_STEPS_FOR_ROUND: u8 = 5;
-const MAX_POWER: u16 = 10_000;
-const MIN_POWER: u16 = 3_000;
+
+mod random;
+mod rating;
+
+const MAX_PLAYERS: usize = 16;
+const SPECIAL_ENERGY_COST: u16 = 500;
+const SPECIAL_MULTIPLIER: u32 = 2;
+const HEAL_AMOUNT: u16 = 1_000;
+const CRIT_CHANCE_PERCENT: u32 = 10;
+const CRIT_MULTIPLIER_PERCENT: u32 = 150;
+const TURN_DURATION: u32 = 20;
+const MAX_TIMEOUTS: u8 = 2;
+const REVEAL_DURATION: u32 = 100;
+const DELAYED_HANDLER_GAS: u64 = 1_000_000_000;
+const LEVEL_POWER_BONUS: u16 = 100;
+const WIN_EXPERIENCE: u64 = 20;
+const LOSS_EXPERIENCE: u64 = 5;
+const FED_DRAIN: u64 = 500;
+const RESTED_DRAIN: u64 = 500;
 
 #[derive(Default, Encode, Decode, TypeInfo)]
 pub struct Battle {
+    admin: ActorId,
+    config: Config,
+    paused: bool,
+    tmg_store_id: ActorId,
+    ft_contract_id: ActorId,
+    entry_fee: u128,
+    fee_collector: ActorId,
+    arena_fee_percent: u8,
+    transaction_id: TransactionId,
+    transactions: BTreeMap<TamagotchiId, TransactionId>,
+    stats: BTreeMap<TamagotchiId, PlayerStats>,
+    rooms: BTreeMap<RoomId, Room>,
+    next_room_id: RoomId,
+    refunds: BTreeMap<ActorId, u128>,
+    refund_claims: BTreeMap<ActorId, (TransactionId, u128)>,
+}
+
+#[derive(Default, Encode, Decode, TypeInfo)]
+pub struct Room {
+    id: RoomId,
+    creator: ActorId,
+    invited: Option<BTreeSet<ActorId>>,
     players: Vec<Player>,
     state: BattleState,
     current_turn: u8,
     winner: ActorId,
     steps: u8,
-    tmg_store_id: ActorId,
+    rounds: Vec<Vec<Match>>,
+    current_match: u32,
+    random: RandomState,
+    moves: Vec<MoveRecord>,
+    turn_number: u64,
+    turn_deadline: u32,
+    prize_pool: u128,
+    arena_fee: u128,
+    stakes: BTreeMap<TamagotchiId, Stake>,
+    payout_transaction_id: Option<TransactionId>,
 }
 
 static mut BATTLE: Option<Battle> = None;
 impl Battle {
-    async fn register(&mut self, tmg_id: &TamagotchiId) {
+    fn create_room(&mut self, invited: Option<BTreeSet<ActorId>>) {
+        self.check_not_paused();
+        let room_id = self.next_room_id;
+        self.next_room_id = self.next_room_id.wrapping_add(1);
+        let room = Room {
+            id: room_id,
+            creator: msg::source(),
+            invited,
+            ..Default::default()
+        };
+        self.rooms.insert(room_id, room);
+        msg::reply(BattleEvent::RoomCreated { room_id }, 0)
+            .expect("Error during a reply `BattleEvent::RoomCreated");
+    }
+
+    fn invite(&mut self, room_id: RoomId, players: BTreeSet<ActorId>) {
+        let room = self.room(room_id);
         assert_eq!(
-            self.state,
+            room.creator,
+            msg::source(),
+            "Only the room creator can invite players"
+        );
+        room.invited
+            .as_mut()
+            .expect("The room is public")
+            .extend(players);
+        msg::reply(BattleEvent::Invited, 0).expect("Error during a reply `BattleEvent::Invited");
+    }
+
+    async fn register(&mut self, room_id: RoomId, tmg_id: &TamagotchiId) {
+        self.check_not_paused();
+        let room = self.room(room_id);
+        assert_eq!(
+            room.state,
             BattleState::Registration,
             "The game has already started"
         );
-
-        let attributes = get_attributes(&self.tmg_store_id, tmg_id).await;
-        let power = genetate_power();
-        let energy = MAX_POWER - power;
+        if let Some(invited) = &room.invited {
+            assert!(
+                room.creator == msg::source() || invited.contains(&msg::source()),
+                "You are not invited to the room"
+            );
+        }
         let owner = get_owner(tmg_id).await;
+        assert_eq!(
+            owner,
+            msg::source(),
+            "Only the Tamagotchi owner can register it"
+        );
+        check_tmg_state(tmg_id).await;
+        let level = get_level(tmg_id).await;
+        let attributes = get_equipped(&self.tmg_store_id, tmg_id).await;
+        let effects = get_effects(&self.tmg_store_id, tmg_id).await;
+        self.check_not_registered(room_id, tmg_id, &owner);
+
+        if self.entry_fee > 0 {
+            let transaction_id = self.pending_transaction(tmg_id);
+            let result = transfer_tokens(
+                transaction_id,
+                &self.ft_contract_id,
+                &owner,
+                &exec::program_id(),
+                self.entry_fee,
+            )
+            .await;
+            if result.is_err() {
+                panic!("Unable to pay the entry fee");
+            }
+            self.transactions.remove(tmg_id);
+            // the Tamagotchi could be registered by another message while the fee was being paid.
+            // The refund gets its own transaction, as the panic reverts only the changes
+            // made after the refund.
+            if let Some(error) = self.registration_error(room_id, tmg_id, &owner) {
+                let transaction_id = self.transaction_id;
+                self.transaction_id = self.transaction_id.wrapping_add(1);
+                let result = transfer_tokens(
+                    transaction_id,
+                    &self.ft_contract_id,
+                    &exec::program_id(),
+                    &owner,
+                    self.entry_fee,
+                )
+                .await;
+                if result.is_ok() {
+                    panic!("{}", error);
+                }
+                // the message doesn't fail, otherwise the claimable refund would be reverted
+                let amount = self.entry_fee;
+                *self.refunds.entry(owner).or_default() += amount;
+                msg::reply(BattleEvent::RefundPending { amount }, 0)
+                    .expect("Error during a reply `BattleEvent::RefundPending");
+                return;
+            }
+            let amount = self.entry_fee;
+            let room = self.room(room_id);
+            room.prize_pool += amount;
+            room.stakes.insert(
+                *tmg_id,
+                Stake {
+                    payer: owner,
+                    amount,
+                },
+            );
+        }
         let player = Player {
             owner,
             tmg_id: *tmg_id,
-            energy,
-            power,
+            level,
             attributes,
+            effects,
+            ..Default::default()
         };
-        self.players.push(player);
-        if self.players.len() == 2 {
-            self.current_turn = get_turn();
-            self.state = BattleState::Moves;
+        let paused = self.paused;
+        let room = self.room(room_id);
+        room.players.push(player);
+        // the arena could be paused while the Tamagotchi was being checked
+        if !paused && room.players.len() >= MAX_PLAYERS && room.random.commitments.is_empty() {
+            self.start_tournament(room_id);
         }
         msg::reply(BattleEvent::Registered { tmg_id: *tmg_id }, 0)
             .expect("Error during a reply `BattleEvent::Registered");
     }
-    fn make_move(&mut self) {
+
+    fn close_room(&mut self, room_id: RoomId) {
+        let admin = self.admin;
+        let room = self.room(room_id);
+        assert!(
+            msg::source() == admin || msg::source() == room.creator,
+            "Only admin or the room creator can close the room"
+        );
+        match room.state {
+            BattleState::GameIsOver => assert!(
+                room.prize_pool == 0 && room.arena_fee == 0,
+                "The prize of the game isn't paid out"
+            ),
+            BattleState::Registration => assert!(
+                room.players.is_empty() && room.stakes.is_empty() && room.prize_pool == 0,
+                "The players are registered, cancel the game first"
+            ),
+            _ => panic!("The game is being played"),
+        }
+        self.rooms.remove(&room_id);
+        msg::reply(BattleEvent::RoomClosed { room_id }, 0)
+            .expect("Error during a reply `BattleEvent::RoomClosed");
+    }
+
+    fn check_not_registered(&self, room_id: RoomId, tmg_id: &TamagotchiId, owner: &ActorId) {
+        if let Some(error) = self.registration_error(room_id, tmg_id, owner) {
+            panic!("{}", error);
+        }
+    }
+
+    // A Tamagotchi can fight in one game at a time and an owner can register one Tamagotchi per room
+    fn registration_error(
+        &self,
+        room_id: RoomId,
+        tmg_id: &TamagotchiId,
+        owner: &ActorId,
+    ) -> Option<&'static str> {
+        if self.rooms.values().any(|room| {
+            room.state != BattleState::GameIsOver
+                && room.players.iter().any(|player| player.tmg_id == *tmg_id)
+        }) {
+            return Some("The Tamagotchi is already registered");
+        }
+        let Some(room) = self.rooms.get(&room_id) else {
+            return Some("The room doesn't exist");
+        };
+        if room.state != BattleState::Registration {
+            return Some("The game has already started");
+        }
+        if room.players.iter().any(|player| player.owner == *owner) {
+            return Some("The owner has already registered a Tamagotchi");
+        }
+        // the full room waits for the committed seeds to be revealed
+        if room.players.len() >= MAX_PLAYERS {
+            return Some("The room is full");
+        }
+        None
+    }
+
+    fn start_tournament_by_player(&mut self, room_id: RoomId) {
+        self.check_not_paused();
+        let room = self.room(room_id);
         assert_eq!(
-            self.state,
+            room.state,
+            BattleState::Registration,
+            "The game has already started"
+        );
+        assert!(
+            room.players
+                .iter()
+                .any(|player| player.owner == msg::source()),
+            "Only registered players can start the tournament"
+        );
+        if !room.random.commitments.is_empty() {
+            assert!(
+                exec::block_height() > room.random.reveal_deadline,
+                "Not all committed seeds are revealed"
+            );
+            room.drop_unrevealed();
+        }
+        assert!(room.players.len() >= 2, "Not enough players");
+        self.start_tournament(room_id);
+        msg::reply(BattleEvent::TournamentStarted, 0)
+            .expect("Error during a reply `BattleEvent::TournamentStarted");
+    }
+
+    // The full room starts the tournament once the last committed seed is revealed,
+    // unless the arena is paused
+    fn reveal_seed(&mut self, room_id: RoomId, seed: [u8; 32]) {
+        let paused = self.paused;
+        let room = self.room(room_id);
+        room.reveal_seed(seed);
+        if !paused && room.players.len() >= MAX_PLAYERS && room.random.commitments.is_empty() {
+            self.start_tournament(room_id);
+        }
+    }
+
+    fn start_tournament(&mut self, room_id: RoomId) {
+        let room = self
+            .rooms
+            .get_mut(&room_id)
+            .expect("The room doesn't exist");
+        room.start_tournament(&mut self.stats, &self.config);
+    }
+
+    // Records the winner of the current match and starts the next one.
+    // The prize is paid out once the champion is determined.
+    async fn finish_match(
+        &mut self,
+        room_id: RoomId,
+        winner: TamagotchiId,
+        last_move: Option<MoveRecord>,
+    ) -> BattleEvent {
+        let room = self.room(room_id);
+        let current_match = room.current_match as usize;
+        if let Some(round) = room.rounds.last_mut() {
+            round[current_match].winner = Some(winner);
+        }
+        let [first, second] = room.fighters().map(|i| room.players[i].tmg_id);
+        let loser = if first == winner { second } else { first };
+        send_battle_result(&winner, true);
+        send_battle_result(&loser, false);
+        let mut winner_stats = self.stats[&winner].clone();
+        let mut loser_stats = self.stats[&loser].clone();
+        rating::record_match(&mut winner_stats, &mut loser_stats);
+        self.stats.insert(winner, winner_stats);
+        self.stats.insert(loser, loser_stats);
+        let arena_fee_percent = self.arena_fee_percent;
+        let config = self.config;
+        let room = self.room(room_id);
+        if room.next_match(&config) {
+            return BattleEvent::MatchIsOver { last_move, winner };
+        }
+        room.arena_fee = room.prize_pool * arena_fee_percent as u128 / 100;
+        room.prize_pool -= room.arena_fee;
+        self.pay_prize(room_id).await;
+        BattleEvent::GameIsOver { last_move, winner }
+    }
+
+    // Transfers the arena fee to the fee collector and the rest of the pool to the champion owner.
+    // Returns the paid prize or `None` if any of the transfers has failed.
+    async fn pay_prize(&mut self, room_id: RoomId) -> Option<u128> {
+        let ft_contract_id = self.ft_contract_id;
+        let fee_collector = self.fee_collector;
+        let room = self
+            .rooms
+            .get_mut(&room_id)
+            .expect("The room doesn't exist");
+        let prize = room.prize_pool;
+        if prize == 0 && room.arena_fee == 0 {
+            return Some(0);
+        }
+        let transaction_id = *room.payout_transaction_id.get_or_insert_with(|| {
+            let transaction_id = self.transaction_id;
+            self.transaction_id = self.transaction_id.wrapping_add(2);
+            transaction_id
+        });
+        if room.arena_fee > 0 {
+            transfer_tokens(
+                transaction_id,
+                &ft_contract_id,
+                &exec::program_id(),
+                &fee_collector,
+                room.arena_fee,
+            )
+            .await
+            .ok()?;
+            room.arena_fee = 0;
+        }
+        if prize > 0 {
+            let owner = room.players[room.player_index(&room.winner)].owner;
+            transfer_tokens(
+                transaction_id.wrapping_add(1),
+                &ft_contract_id,
+                &exec::program_id(),
+                &owner,
+                prize,
+            )
+            .await
+            .ok()?;
+            room.prize_pool = 0;
+        }
+        room.payout_transaction_id = None;
+        Some(prize)
+    }
+
+    async fn pay_prize_by_player(&mut self, room_id: RoomId) {
+        assert_eq!(
+            self.room(room_id).state,
+            BattleState::GameIsOver,
+            "The game must be over"
+        );
+        let amount = self
+            .pay_prize(room_id)
+            .await
+            .expect("Unable to pay out the prize");
+        let room = self.room(room_id);
+        let winner = room.players[room.player_index(&room.winner)].owner;
+        msg::reply(BattleEvent::PrizePaid { winner, amount }, 0)
+            .expect("Error during a reply `BattleEvent::PrizePaid");
+    }
+
+    async fn cancel_game(&mut self, room_id: RoomId) {
+        let admin = self.admin;
+        let room = self.room(room_id);
+        assert!(
+            msg::source() == admin || msg::source() == room.creator,
+            "Only admin or the room creator can cancel the game"
+        );
+        assert!(
+            room.state == BattleState::Registration || room.state == BattleState::Refunding,
+            "The game has already started"
+        );
+        self.refund_stakes(room_id).await;
+        msg::reply(BattleEvent::GameCancelled, 0)
+            .expect("Error during a reply `BattleEvent::GameCancelled");
+    }
+
+    async fn reset_game(&mut self, room_id: RoomId) {
+        self.check_admin();
+        let room = self.room(room_id);
+        assert_ne!(
+            room.state,
+            BattleState::GameIsOver,
+            "The game is over, start a new one"
+        );
+        // the delayed messages of the stopped game have no effect
+        // in the `Refunding` and `Registration` states
+        room.winner = ActorId::zero();
+        room.current_turn = 0;
+        room.steps = 0;
+        room.rounds = Vec::new();
+        room.current_match = 0;
+        room.moves = Vec::new();
+        self.refund_stakes(room_id).await;
+        msg::reply(BattleEvent::GameReset, 0)
+            .expect("Error during a reply `BattleEvent::GameReset");
+    }
+
+    // Refunds the entry fees and removes the players.
+    // The room takes no registrations until all fees are refunded.
+    // A failed refund keeps its pending transaction,
+    // so cancelling or resetting the game again retries it with the same transaction ID.
+    async fn refund_stakes(&mut self, room_id: RoomId) {
+        self.room(room_id).state = BattleState::Refunding;
+        for (tmg_id, stake) in self.room(room_id).stakes.clone() {
+            let transaction_id = self.pending_transaction(&tmg_id);
+            let result = transfer_tokens(
+                transaction_id,
+                &self.ft_contract_id,
+                &exec::program_id(),
+                &stake.payer,
+                stake.amount,
+            )
+            .await;
+            assert!(result.is_ok(), "Unable to refund the entry fee");
+            self.transactions.remove(&tmg_id);
+            let room = self.room(room_id);
+            room.stakes.remove(&tmg_id);
+            room.prize_pool -= stake.amount;
+        }
+        let room = self.room(room_id);
+        room.players = Vec::new();
+        room.random.commitments.clear();
+        room.state = BattleState::Registration;
+    }
+
+    async fn claim_refund(&mut self) {
+        let account = msg::source();
+        // the previous claim may have been interrupted
+        let (transaction_id, amount) = if let Some(claim) = self.refund_claims.get(&account) {
+            *claim
+        } else {
+            let amount = self.refunds.remove(&account).expect("There is no refund");
+            let transaction_id = self.transaction_id;
+            self.transaction_id = self.transaction_id.wrapping_add(1);
+            self.refund_claims.insert(account, (transaction_id, amount));
+            (transaction_id, amount)
+        };
+        let result = transfer_tokens(
+            transaction_id,
+            &self.ft_contract_id,
+            &exec::program_id(),
+            &account,
+            amount,
+        )
+        .await;
+        assert!(result.is_ok(), "Unable to refund, claim the refund again");
+        self.refund_claims.remove(&account);
+        msg::reply(BattleEvent::RefundClaimed { amount }, 0)
+            .expect("Error during a reply `BattleEvent::RefundClaimed");
+    }
+
+    fn update_config(&mut self, config: Config) {
+        self.check_admin();
+        check_config(&config);
+        self.config = config;
+        msg::reply(BattleEvent::ConfigUpdated, 0)
+            .expect("Error during a reply `BattleEvent::ConfigUpdated");
+    }
+
+    fn set_tmg_store(&mut self, tmg_store_id: ActorId) {
+        self.check_admin();
+        self.tmg_store_id = tmg_store_id;
+        msg::reply(BattleEvent::TmgStoreSet, 0)
+            .expect("Error during a reply `BattleEvent::TmgStoreSet");
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.check_admin();
+        self.paused = paused;
+        let event = if paused {
+            BattleEvent::Paused
+        } else {
+            BattleEvent::Unpaused
+        };
+        msg::reply(event, 0).expect("Error during a reply `BattleEvent::Paused");
+    }
+
+    fn check_admin(&self) {
+        assert_eq!(msg::source(), self.admin, "Only admin can do that");
+    }
+
+    fn check_not_paused(&self) {
+        assert!(!self.paused, "The arena is paused");
+    }
+
+    // Returns the ID of the pending transaction with the Tamagotchi stake or allocates a new one
+    fn pending_transaction(&mut self, tmg_id: &TamagotchiId) -> TransactionId {
+        if let Some(transaction_id) = self.transactions.get(tmg_id) {
+            return *transaction_id;
+        }
+        let transaction_id = self.transaction_id;
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        self.transactions.insert(*tmg_id, transaction_id);
+        transaction_id
+    }
+
+    async fn surrender(&mut self, room_id: RoomId) {
+        let room = self.room(room_id);
+        assert!(
+            room.state == BattleState::Moves || room.state == BattleState::Waiting,
+            "The match isn't being fought"
+        );
+        let fighters = room.fighters();
+        let loser = fighters
+            .iter()
+            .position(|i| room.players[*i].owner == msg::source())
+            .expect("You are not in the current match");
+        let winner = room.players[fighters[1 - loser]].tmg_id;
+        let event = self.finish_match(room_id, winner, None).await;
+        msg::reply(event, 0).expect("Error in sending a reply `BattleEvent::MatchIsOver`");
+    }
+
+    async fn turn_timeout(&mut self, room_id: RoomId, turn_number: u64) {
+        let config = self.config;
+        // the check scheduled by the program ignores the turns that are over,
+        // but the opponent claiming the timeout must be told
+        let scheduled = msg::source() == exec::program_id();
+        let Some(room) = self.rooms.get_mut(&room_id) else {
+            // the room may have been closed after the game
+            assert!(scheduled, "The room doesn't exist");
+            return;
+        };
+        // the move has already been made
+        if room.state != BattleState::Moves || room.turn_number != turn_number {
+            assert!(scheduled, "The turn is over");
+            return;
+        }
+        let fighters = room.fighters();
+        let turn = room.current_turn as usize;
+        if !scheduled {
+            assert_eq!(
+                room.players[fighters[1 - turn]].owner,
+                msg::source(),
+                "Only the opponent can claim the timeout"
+            );
+            assert!(
+                exec::block_height() >= room.turn_deadline,
+                "The time for the move isn't over"
+            );
+        }
+        let player = &mut room.players[fighters[turn]];
+        player.timeouts += 1;
+        let event = if player.timeouts >= MAX_TIMEOUTS {
+            let winner = room.players[fighters[1 - turn]].tmg_id;
+            self.finish_match(room_id, winner, None).await
+        } else {
+            let tmg_id = player.tmg_id;
+            room.current_turn = (1 - turn) as u8;
+            room.begin_turn(&config);
+            BattleEvent::TurnSkipped { tmg_id }
+        };
+        msg::reply(event, 0).expect("Error in sending a reply `BattleEvent::TurnSkipped`");
+    }
+
+    async fn make_move(&mut self, room_id: RoomId, kind: Move) {
+        let config = self.config;
+        let room = self.room(room_id);
+        assert_eq!(
+            room.state,
             BattleState::Moves,
             "The game is not in `Moves` state"
         );
-        let turn = self.current_turn as usize;
+        let fighters = room.fighters();
+        let turn = room.current_turn as usize;
         let next_turn = (turn + 1) % 2;
-        let player = self.players[turn].clone();
+        let mut player = room.players[fighters[turn]].clone();
         assert_eq!(
             player.owner,
             msg::source(),
             "You are not in the game or it is not your turn"
         );
-        let mut opponent = self.players[next_turn].clone();
-        let sword_power = if player.attributes.contains(&SWORD_ID) {
-            SWORD_POWER
-        } else {
-            1
-        };
+        let mut opponent = room.players[fighters[next_turn]].clone();
+        assert_eq!(
+            player.cooldowns.get(&kind).copied().unwrap_or_default(),
+            0,
+            "The move is on cooldown"
+        );
+        for cooldown in player.cooldowns.values_mut() {
+            *cooldown = cooldown.saturating_sub(1);
+        }
+        // the defense lasts until the player's next move
+        player.defending = false;
 
-        opponent.energy = opponent
-            .energy
-            .saturating_sub((sword_power * player.power) / 5);
-        self.players[next_turn] = opponent.clone();
+        let mut dealt = 0;
+        let mut critical = false;
+        match kind {
+            Move::Attack | Move::Special => {
+                let mut damage = damage(&player, &opponent) as u32;
+                if kind == Move::Special {
+                    assert!(
+                        player.energy > SPECIAL_ENERGY_COST,
+                        "Not enough energy for the special move"
+                    );
+                    player.energy -= SPECIAL_ENERGY_COST;
+                    damage *= SPECIAL_MULTIPLIER;
+                }
+                critical = random::random_below(&mut room.random, player.tmg_id.as_ref(), 100)
+                    < CRIT_CHANCE_PERCENT;
+                if critical {
+                    damage = damage * CRIT_MULTIPLIER_PERCENT / 100;
+                }
+                if opponent.defending {
+                    damage /= 2;
+                }
+                dealt = damage.min(u16::MAX as u32) as u16;
+                opponent.energy = opponent.energy.saturating_sub(dealt);
+            }
+            Move::Defend => player.defending = true,
+            Move::Heal => {
+                player.energy = player
+                    .energy
+                    .saturating_add(HEAL_AMOUNT)
+                    .min(initial_energy(&config, player.power, &player.effects));
+            }
+        }
+        if let Some(cooldown) = cooldown(kind) {
+            player.cooldowns.insert(kind, cooldown);
+        }
+        let record = MoveRecord {
+            round: room.rounds.len() as u32 - 1,
+            match_index: room.current_match,
+            step: room.steps,
+            attacker: player.tmg_id,
+            defender: opponent.tmg_id,
+            kind,
+            damage: dealt,
+            critical,
+            attacker_energy: player.energy,
+            defender_energy: opponent.energy,
+        };
+        room.moves.push(record.clone());
+        room.players[fighters[turn]] = player.clone();
+        room.players[fighters[next_turn]] = opponent.clone();
         // check if opponent lost
         if opponent.energy == 0 {
-            self.state = BattleState::GameIsOver;
-            self.winner = player.tmg_id;
-            msg::reply(BattleEvent::GameIsOver, 0)
-                .expect("Error in sending a reply `BattleEvent::GameIsOver`");
+            let event = self
+                .finish_match(room_id, player.tmg_id, Some(record))
+                .await;
+            msg::reply(event, 0).expect("Error in sending a reply `BattleEvent::MatchIsOver`");
             return;
         }
-        if self.steps <= MAX_STEPS_FOR_ROUND {
-            self.steps += 1;
-            self.current_turn = next_turn as u8;
-            msg::reply(BattleEvent::MoveMade, 0)
+        if room.steps <= config.max_steps_for_round {
+            room.steps += 1;
+            room.current_turn = next_turn as u8;
+            room.begin_turn(&config);
+            msg::reply(BattleEvent::MoveMade(record), 0)
                 .expect("Error in sending a reply `BattleEvent::MoveMade`");
         } else {
-            self.state = BattleState::Waiting;
-            self.steps = 0;
+            room.state = BattleState::Waiting;
+            room.steps = 0;
             msg::send_with_gas_delayed(
                 exec::program_id(),
-                BattleAction::UpdateInfo,
-                GAS_AMOUNT,
+                BattleAction::UpdateInfo { room_id },
+                config.gas_amount,
                 0,
-                TIME_FOR_UPDATE,
+                config.time_for_update,
             )
             .expect("Error in sending a delayed message `BattleAction::UpdateInfo`");
-            msg::reply(BattleEvent::GoToWaitingState, 0)
-                .expect("Error in sending a reply `BattleEvent::MoveMade`");
+            msg::reply(BattleEvent::GoToWaitingState(record), 0)
+                .expect("Error in sending a reply `BattleEvent::GoToWaitingState`");
         }
     }
-    async fn update_info(&mut self) {
+
+    async fn update_info(&mut self, room_id: RoomId) {
         assert_eq!(
             msg::source(),
             exec::program_id(),
             "Only contract itself can call that action"
         );
+        let tmg_store_id = self.tmg_store_id;
+        let config = self.config;
+        let room = self.room(room_id);
         assert_eq!(
-            self.state,
+            room.state,
             BattleState::Waiting,
             "The contract must be in `Waiting` state"
         );
 
-        for i in 0..2 {
-            let player = &mut self.players[i];
-            let attributes = get_attributes(&self.tmg_store_id, &player.tmg_id).await;
-            player.attributes = attributes;
+        for i in room.fighters() {
+            let player = &mut room.players[i];
+            player.attributes = get_equipped(&tmg_store_id, &player.tmg_id).await;
+            player.effects = get_effects(&tmg_store_id, &player.tmg_id).await;
+            // the heal can't restore more energy than the player had at the start of the match
+            player.energy = player
+                .energy
+                .saturating_add(player.effects.heal)
+                .min(initial_energy(&config, player.power, &player.effects));
         }
-        self.state = BattleState::Moves;
-        self.current_turn = get_turn();
+        room.state = BattleState::Moves;
+        room.current_turn = get_turn(&mut room.random);
+        room.begin_turn(&config);
         msg::reply(BattleEvent::InfoUpdated, 0)
             .expect("Error during a reply `BattleEvent::InfoUpdated");
     }
 
+    fn room(&mut self, room_id: RoomId) -> &mut Room {
+        self.rooms
+            .get_mut(&room_id)
+            .expect("The room doesn't exist")
+    }
+}
+
+impl Room {
+    fn commit_seed(&mut self, hash: [u8; 32]) {
+        assert_eq!(
+            self.state,
+            BattleState::Registration,
+            "The game has already started"
+        );
+        let player = msg::source();
+        assert!(
+            self.players.iter().any(|p| p.owner == player),
+            "Only registered players can commit seeds"
+        );
+        assert!(
+            self.random.commitments.insert(player, hash).is_none(),
+            "The seed is already committed"
+        );
+        self.random.reveal_deadline = exec::block_height() + REVEAL_DURATION;
+        msg::reply(BattleEvent::SeedCommitted, 0)
+            .expect("Error during a reply `BattleEvent::SeedCommitted");
+    }
+
+    fn reveal_seed(&mut self, seed: [u8; 32]) {
+        assert_eq!(
+            self.state,
+            BattleState::Registration,
+            "The game has already started"
+        );
+        let hash = self
+            .random
+            .commitments
+            .remove(&msg::source())
+            .expect("The seed isn't committed");
+        assert_eq!(
+            random::hash(&seed),
+            hash,
+            "The seed doesn't match the commitment"
+        );
+        random::mix_seed(&mut self.random, &seed);
+        msg::reply(BattleEvent::SeedRevealed, 0)
+            .expect("Error during a reply `BattleEvent::SeedRevealed");
+    }
+
+    // The players who haven't revealed their seeds in time are removed.
+    // Their stakes are kept, so the entry fees go to the winner with the prize pool
+    // or are refunded if the game is reset.
+    fn drop_unrevealed(&mut self) {
+        let commitments = mem::take(&mut self.random.commitments);
+        self.players
+            .retain(|player| !commitments.contains_key(&player.owner));
+    }
+
+    // Generates the powers of the players and the first round of the bracket.
+    // The players are seeded by their ratings: the number of players is completed
+    // to a power of two by byes given to the highest rated players,
+    // the others are paired with the players of the closest ratings.
+    fn start_tournament(
+        &mut self,
+        stats: &mut BTreeMap<TamagotchiId, PlayerStats>,
+        config: &Config,
+    ) {
+        for player in self.players.iter_mut() {
+            player.power = genetate_power(config, &mut self.random, &player.tmg_id, player.level);
+            stats
+                .entry(player.tmg_id)
+                .or_insert_with(rating::new_stats)
+                .games += 1;
+        }
+        let mut seeds: Vec<TamagotchiId> =
+            self.players.iter().map(|player| player.tmg_id).collect();
+        seeds.sort_by(|a, b| stats[b].rating.cmp(&stats[a].rating));
+        let byes = seeds.len().next_power_of_two() - seeds.len();
+        let (seeds_with_bye, seeds) = seeds.split_at(byes);
+        let first_round = seeds_with_bye
+            .iter()
+            .map(|tmg_id| Match::new(*tmg_id, None))
+            .chain(
+                seeds
+                    .chunks(2)
+                    .map(|pair| Match::new(pair[0], pair.get(1).copied())),
+            )
+            .collect();
+        self.rounds = vec![first_round];
+        self.next_match(config);
+    }
+
+    // Starts the next match to be fought, generating the next round if the current one is over.
+    // Returns `false` if the champion is determined.
+    fn next_match(&mut self, config: &Config) -> bool {
+        loop {
+            let round = self.rounds.last().expect("The tournament has started");
+            if let Some(index) = round.iter().position(|m| m.winner.is_none()) {
+                self.current_match = index as u32;
+                self.start_match(config);
+                return true;
+            }
+            let winners: Vec<TamagotchiId> = round.iter().filter_map(|m| m.winner).collect();
+            if let [champion] = winners[..] {
+                self.winner = champion;
+                self.state = BattleState::GameIsOver;
+                return false;
+            }
+            let next_round = winners
+                .chunks(2)
+                .map(|pair| Match::new(pair[0], pair.get(1).copied()))
+                .collect();
+            self.rounds.push(next_round);
+        }
+    }
+
+    // The fighters restore their energy before each match
+    fn start_match(&mut self, config: &Config) {
+        for i in self.fighters() {
+            let player = &mut self.players[i];
+            player.energy = initial_energy(config, player.power, &player.effects);
+            player.cooldowns.clear();
+            player.defending = false;
+            player.timeouts = 0;
+        }
+        self.steps = 0;
+        self.current_turn = get_turn(&mut self.random);
+        self.state = BattleState::Moves;
+        self.begin_turn(config);
+    }
+
+    // Sets the deadline of the turn and schedules the check that the move is made in time.
+    // If there isn't enough gas for the check, the opponent claims the timeout after the deadline.
+    fn begin_turn(&mut self, config: &Config) {
+        self.turn_number = self.turn_number.wrapping_add(1);
+        self.turn_deadline = exec::block_height() + TURN_DURATION;
+        let Some(gas) = delayed_message_gas(config) else {
+            return;
+        };
+        msg::send_with_gas_delayed(
+            exec::program_id(),
+            BattleAction::TurnTimeout {
+                room_id: self.id,
+                turn_number: self.turn_number,
+            },
+            gas,
+            0,
+            TURN_DURATION,
+        )
+        .expect("Error in sending a delayed message `BattleAction::TurnTimeout`");
+    }
+
+    // Returns the indices of the players fighting in the current match
+    fn fighters(&self) -> [usize; 2] {
+        let current_match =
+            &self.rounds.last().expect("The tournament has started")[self.current_match as usize];
+        let (first, second) = current_match.tamagotchis;
+        let second = second.expect("Matches with a bye aren't fought");
+        [self.player_index(&first), self.player_index(&second)]
+    }
+
+    fn player_index(&self, tmg_id: &TamagotchiId) -> usize {
+        self.players
+            .iter()
+            .position(|player| player.tmg_id == *tmg_id)
+            .expect("The Tamagotchi is registered")
+    }
+
     fn start_new_game(&mut self) {
         assert_eq!(
             self.state,
             BattleState::GameIsOver,
             "The previous game must be over"
         );
+        assert!(
+            self.prize_pool == 0 && self.arena_fee == 0,
+            "The prize of the previous game isn't paid out"
+        );
         self.winner = ActorId::zero();
         self.current_turn = 0;
         self.players = Vec::new();
+        self.rounds = Vec::new();
+        self.current_match = 0;
+        self.random.commitments.clear();
+        self.moves = Vec::new();
+        self.stakes = BTreeMap::new();
         self.state = BattleState::Registration;
         msg::reply(BattleEvent::NewGame, 0).expect("Error during a reply `BattleEvent::NewGame");
     }
@@ -146,18 +926,51 @@ async fn main() {
     let action: BattleAction = msg::load().expect("Unable to decode `BattleAction`");
     let battle = unsafe { BATTLE.get_or_insert(Default::default()) };
     match action {
-        BattleAction::Register { tmg_id } => battle.register(&tmg_id).await,
-        BattleAction::MakeMove => battle.make_move(),
-        BattleAction::UpdateInfo => battle.update_info().await,
-        BattleAction::StartNewGame => battle.start_new_game(),
+        BattleAction::CreateRoom { invited } => battle.create_room(invited),
+        BattleAction::Invite { room_id, players } => battle.invite(room_id, players),
+        BattleAction::Register { room_id, tmg_id } => battle.register(room_id, &tmg_id).await,
+        BattleAction::CloseRoom { room_id } => battle.close_room(room_id),
+        BattleAction::StartTournament { room_id } => battle.start_tournament_by_player(room_id),
+        BattleAction::CommitSeed { room_id, hash } => battle.room(room_id).commit_seed(hash),
+        BattleAction::RevealSeed { room_id, seed } => battle.reveal_seed(room_id, seed),
+        BattleAction::MakeMove { room_id, kind } => battle.make_move(room_id, kind).await,
+        BattleAction::Surrender { room_id } => battle.surrender(room_id).await,
+        BattleAction::TurnTimeout {
+            room_id,
+            turn_number,
+        } => battle.turn_timeout(room_id, turn_number).await,
+        BattleAction::UpdateInfo { room_id } => battle.update_info(room_id).await,
+        BattleAction::StartNewGame { room_id } => {
+            battle.check_not_paused();
+            battle.room(room_id).start_new_game()
+        }
+        BattleAction::PayPrize { room_id } => battle.pay_prize_by_player(room_id).await,
+        BattleAction::CancelGame { room_id } => battle.cancel_game(room_id).await,
+        BattleAction::UpdateConfig(config) => battle.update_config(config),
+        BattleAction::SetTmgStore { tmg_store_id } => battle.set_tmg_store(tmg_store_id),
+        BattleAction::Pause => battle.set_paused(true),
+        BattleAction::Unpause => battle.set_paused(false),
+        BattleAction::ResetGame { room_id } => battle.reset_game(room_id).await,
+        BattleAction::ClaimRefund => battle.claim_refund().await,
     }
 }
 
 #[no_mangle]
 extern "C" fn init() {
-    let tmg_store_id: ActorId = msg::load().expect("Unable to decode `ActorId");
+    let init: InitBattle = msg::load().expect("Unable to decode `InitBattle`");
+    assert!(
+        init.arena_fee_percent <= 100,
+        "The arena fee can't exceed 100 percent"
+    );
+    check_config(&init.config);
     let battle = Battle {
-        tmg_store_id,
+        tmg_store_id: init.tmg_store_id,
+        admin: msg::source(),
+        config: init.config,
+        ft_contract_id: init.ft_contract_id,
+        entry_fee: init.entry_fee,
+        fee_collector: init.fee_collector,
+        arena_fee_percent: init.arena_fee_percent,
         ..Default::default()
     };
     unsafe { BATTLE = Some(battle) };
@@ -175,37 +988,183 @@ pub async fn get_owner(tmg_id: &ActorId) -> ActorId {
     }
 }
 
-pub fn get_turn() -> u8 {
-    let random_input: [u8; 32] = array::from_fn(|i| i as u8 + 1);
-    let (random, _) = exec::random(random_input).expect("Error in getting random number");
-    random[0] % 2
+async fn transfer_tokens(
+    transaction_id: TransactionId,
+    token_address: &ActorId,
+    from: &ActorId,
+    to: &ActorId,
+    amount_tokens: u128,
+) -> Result<(), ()> {
+    let reply = msg::send_for_reply_as::<_, FTokenEvent>(
+        *token_address,
+        FTokenAction::Message {
+            transaction_id,
+            payload: LogicAction::Transfer {
+                sender: *from,
+                recipient: *to,
+                amount: amount_tokens,
+            },
+        },
+        0,
+        0,
+    )
+    .expect("Error in sending a message `FTokenAction::Message`")
+    .await;
+
+    match reply {
+        Ok(FTokenEvent::Ok) => Ok(()),
+        _ => Err(()),
+    }
+}
+
+fn check_config(config: &Config) {
+    assert!(
+        config.min_power < config.max_power,
+        "The minimum power must be less than the maximum power"
+    );
+    assert!(
+        config.max_steps_for_round > 0,
+        "The round must have at least one step"
+    );
+}
+
+// The delayed messages the program sends to itself have `gas_amount` of gas,
+// so the ones they send in turn get the gas left except the gas to finish the handler.
+// Returns `None` if the left gas isn't enough to handle the message.
+fn delayed_message_gas(config: &Config) -> Option<u64> {
+    if msg::source() != exec::program_id() {
+        return Some(config.gas_amount);
+    }
+    exec::gas_available()
+        .checked_sub(DELAYED_HANDLER_GAS)
+        .filter(|gas| *gas >= DELAYED_HANDLER_GAS)
+}
+
+pub fn initial_energy(config: &Config, power: u16, effects: &Effects) -> u16 {
+    config
+        .max_power
+        .saturating_sub(power)
+        .saturating_add(effects.energy_bonus)
+}
+
+// The base damage is a fifth of the attacker power,
+// it is scaled by the attacker attack multiplier and reduced by the defender defense.
+pub fn damage(attacker: &Player, defender: &Player) -> u16 {
+    let damage = attacker.power as u32 * attacker.effects.attack_multiplier as u32 / 100 / 5;
+    let damage = damage.saturating_sub(defender.effects.defense as u32);
+    damage.min(u16::MAX as u32) as u16
+}
+
+// Returns the number of the player's next moves during which the move can't be repeated
+pub fn cooldown(kind: Move) -> Option<u8> {
+    match kind {
+        Move::Attack => None,
+        Move::Defend => Some(1),
+        Move::Heal | Move::Special => Some(3),
+    }
+}
+
+pub fn get_turn(random: &mut RandomState) -> u8 {
+    random::random_below(random, b"turn", 2) as u8
 }
 
-pub fn genetate_power() -> u16 {
-    let random_input: [u8; 32] = array::from_fn(|i| i as u8 + 1);
-    let (random, _) = exec::random(random_input).expect("Error in getting random number");
-    let bytes: [u8; 2] = [random[0], random[1]];
-    let random_power: u16 = u16::from_be_bytes(bytes) % MAX_POWER;
-    if random_power < MIN_POWER {
-        return MAX_POWER / 2;
+// Each level adds to the random power, but the power stays low enough
+// for the Tamagotchi to have energy at the start of a match
+pub fn genetate_power(
+    config: &Config,
+    random: &mut RandomState,
+    tmg_id: &TamagotchiId,
+    level: u32,
+) -> u16 {
+    let mut random_power =
+        random::random_below(random, tmg_id.as_ref(), config.max_power as u32) as u16;
+    if random_power < config.min_power {
+        random_power = config.max_power / 2;
     }
+    let max_level_power = config.max_power - config.max_power / 10;
+    let bonus = level
+        .saturating_mul(LEVEL_POWER_BONUS as u32)
+        .min(config.max_power as u32) as u16;
     random_power
+        .saturating_add(bonus)
+        .min(max_level_power.max(random_power))
+}
+
+// The Tamagotchis that don't support the levels (the request fails or has another reply)
+// fight at the level 0
+async fn get_level(tmg_id: &TamagotchiId) -> u32 {
+    let reply = msg::send_for_reply_as::<_, TmgEvent>(*tmg_id, TmgAction::Level, 0, 0)
+        .expect("Error in sending a message `TmgAction::Level`")
+        .await;
+    if let Ok(TmgEvent::Level(level)) = reply {
+        level
+    } else {
+        0
+    }
+}
+
+// The fight makes the Tamagotchi hungry and tired and gives it experience
+fn send_battle_result(tmg_id: &TamagotchiId, won: bool) {
+    msg::send(
+        *tmg_id,
+        TmgAction::BattleResult {
+            won,
+            fed_drain: FED_DRAIN,
+            rested_drain: RESTED_DRAIN,
+            experience: if won { WIN_EXPERIENCE } else { LOSS_EXPERIENCE },
+        },
+        0,
+    )
+    .expect("Error in sending a message `TmgAction::BattleResult`");
+}
+
+// Hungry or tired Tamagotchis can't fight.
+// The Tamagotchis that don't support the check (it fails or has another reply) are considered fit.
+async fn check_tmg_state(tmg_id: &TamagotchiId) {
+    let reply = msg::send_for_reply_as::<_, TmgEvent>(*tmg_id, TmgAction::CheckState, 0, 0)
+        .expect("Error in sending a message `TmgAction::CheckState`")
+        .await;
+    match reply {
+        Ok(TmgEvent::FeedMe) => panic!("The Tamagotchi is hungry"),
+        Ok(TmgEvent::WantToSleep) => panic!("The Tamagotchi is tired"),
+        _ => (),
+    }
+}
+
+// Only the attributes the Tamagotchi has equipped take part in the battle
+async fn get_equipped(tmg_store_id: &ActorId, tmg_id: &TamagotchiId) -> BTreeSet<AttributeId> {
+    let reply: StoreEvent = msg::send_for_reply_as(
+        *tmg_store_id,
+        StoreAction::GetEquipped {
+            tamagotchi_id: *tmg_id,
+        },
+        0,
+        0,
+    )
+    .expect("Error in sending a message `StoreAction::GetEquipped")
+    .await
+    .expect("Unable to decode `StoreEvent`");
+    if let StoreEvent::Equipped { equipped } = reply {
+        equipped.into_values().collect()
+    } else {
+        panic!("Wrong received message");
+    }
 }
 
-async fn get_attributes(tmg_store_id: &ActorId, tmg_id: &TamagotchiId) -> BTreeSet<AttributeId> {
+async fn get_effects(tmg_store_id: &ActorId, tmg_id: &TamagotchiId) -> Effects {
     let reply: StoreEvent = msg::send_for_reply_as(
         *tmg_store_id,
-        StoreAction::GetAttributes {
+        StoreAction::GetEffects {
             tamagotchi_id: *tmg_id,
         },
         0,
         0,
     )
-    .expect("Error in sending a message `StoreAction::GetAttributes")
+    .expect("Error in sending a message `StoreAction::GetEffects")
     .await
     .expect("Unable to decode `StoreEvent`");
-    if let StoreEvent::Attributes { attributes } = reply {
-        attributes
+    if let StoreEvent::Effects { effects } = reply {
+        effects
     } else {
         panic!("Wrong received message");
     }
@@ -0,0 +1,44 @@
+//! Randomness of the battle.
+//!
+//! `exec::random` returns the same value for the same subject within a block,
+//! so the subject of each call mixes a nonce increased on every call,
+//! the id of the current message, the given context (e.g. the Tamagotchi id)
+//! and the seed revealed by the players.
+//!
+//! The tournament doesn't start until every committed seed is revealed,
+//! but the last player to reveal knows the other seeds and can still withhold
+//! their own seed. That costs them the place in the tournament and the entry fee,
+//! and it only lets them pick one of two outcomes they can't otherwise influence.
+
+use battle_io::RandomState;
+use blake2_rfc::blake2b::blake2b;
+use gstd::{exec, msg, prelude::*};
+
+pub fn random_bytes(state: &mut RandomState, context: &[u8]) -> [u8; 32] {
+    state.nonce = state.nonce.wrapping_add(1);
+    let mut subject = state.nonce.encode();
+    subject.extend_from_slice(msg::id().as_ref());
+    subject.extend_from_slice(&state.seed);
+    subject.extend_from_slice(context);
+    let (random, _) = exec::random(hash(&subject)).expect("Error in getting random number");
+    random
+}
+
+/// Returns a random number in `0..max`.
+pub fn random_below(state: &mut RandomState, context: &[u8], max: u32) -> u32 {
+    let random = random_bytes(state, context);
+    u32::from_be_bytes([random[0], random[1], random[2], random[3]]) % max
+}
+
+/// Mixes the seed revealed by a player into the common seed.
+pub fn mix_seed(state: &mut RandomState, seed: &[u8; 32]) {
+    let mut data = state.seed.to_vec();
+    data.extend_from_slice(seed);
+    state.seed = hash(&data);
+}
+
+pub fn hash(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0; 32];
+    hash.copy_from_slice(blake2b(32, &[], data).as_bytes());
+    hash
+}
@@ -0,0 +1,46 @@
+//! ELO ratings of the Tamagotchis.
+//!
+//! The expected score is `1 / (1 + 10^(-diff / 400))`. It is taken from a table
+//! of per mille values for each 50 points of the rating difference
+//! and linearly interpolated between them, so that no floats are used.
+
+use battle_io::PlayerStats;
+
+const INITIAL_RATING: u32 = 1_200;
+const K_FACTOR: u32 = 32;
+const STEP: u32 = 50;
+const EXPECTED_SCORE: [u32; 17] = [
+    500, 571, 640, 703, 760, 808, 849, 882, 909, 930, 947, 960, 969, 977, 983, 987, 990,
+];
+
+/// Returns the expected score (per mille) of the player with `rating` against `opponent`.
+pub fn expected_score(rating: u32, opponent: u32) -> u32 {
+    if rating < opponent {
+        return 1_000 - expected_score(opponent, rating);
+    }
+    let diff = rating - opponent;
+    let index = (diff / STEP) as usize;
+    if index + 1 >= EXPECTED_SCORE.len() {
+        return EXPECTED_SCORE[EXPECTED_SCORE.len() - 1];
+    }
+    let (low, high) = (EXPECTED_SCORE[index], EXPECTED_SCORE[index + 1]);
+    low + (high - low) * (diff % STEP) / STEP
+}
+
+pub fn new_stats() -> PlayerStats {
+    PlayerStats {
+        wins: 0,
+        losses: 0,
+        games: 0,
+        rating: INITIAL_RATING,
+    }
+}
+
+/// Updates the stats of the players of the fought match.
+pub fn record_match(winner: &mut PlayerStats, loser: &mut PlayerStats) {
+    let change = K_FACTOR * (1_000 - expected_score(winner.rating, loser.rating)) / 1_000;
+    winner.rating += change;
+    loser.rating = loser.rating.saturating_sub(change);
+    winner.wins += 1;
+    loser.losses += 1;
+}
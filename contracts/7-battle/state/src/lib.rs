@@ -0,0 +1,20 @@
+#![no_std]
+
+use battle_io::*;
+use gmeta::metawasm;
+use gstd::prelude::*;
+use store_io::TamagotchiId;
+
+#[metawasm]
+pub mod metafns {
+    pub type State = Battle;
+
+    /// Returns the `n` Tamagotchis with the highest ratings.
+    pub fn leaderboard(state: State, n: u32) -> Vec<(TamagotchiId, PlayerStats)> {
+        state.leaderboard(n as usize)
+    }
+
+    pub fn stats_of(state: State, tmg_id: TamagotchiId) -> Option<PlayerStats> {
+        state.stats_of(&tmg_id)
+    }
+}
@@ -0,0 +1,696 @@
+use battle_io::*;
+use gstd::ActorId;
+use utils::*;
+
+mod utils;
+
+#[test]
+fn bracket_with_bye() {
+    let sys = init_system(0);
+    register_players(&sys, ROOM_ID, 3);
+    // only the registered players can start the tournament
+    assert!(start_tournament(&sys, STRANGER, ROOM_ID).main_failed());
+
+    let res = start_tournament(&sys, owner(0), ROOM_ID);
+    assert!(replied(&res, owner(0), BattleEvent::TournamentStarted));
+
+    // the ratings are equal, so the first registered Tamagotchi gets the bye
+    let [first, second, third] = [0, 1, 2].map(|player| ActorId::from(tmg_id(player)));
+    let room = room(&sys, ROOM_ID);
+    assert_eq!(
+        room.rounds,
+        vec![vec![
+            Match::new(first, None),
+            Match::new(second, Some(third))
+        ]]
+    );
+    assert_eq!(room.current_match, 1);
+
+    let res = surrender(&sys, owner(1), ROOM_ID);
+    assert!(replied(
+        &res,
+        owner(1),
+        BattleEvent::MatchIsOver {
+            last_move: None,
+            winner: third
+        }
+    ));
+    let room = utils::room(&sys, ROOM_ID);
+    assert_eq!(room.rounds.len(), 2);
+    assert_eq!(room.rounds[1], vec![Match::new(first, Some(third))]);
+
+    let res = surrender(&sys, owner(0), ROOM_ID);
+    assert!(replied(
+        &res,
+        owner(0),
+        BattleEvent::GameIsOver {
+            last_move: None,
+            winner: third
+        }
+    ));
+    let room = utils::room(&sys, ROOM_ID);
+    assert_eq!(room.state, BattleState::GameIsOver);
+    assert_eq!(room.winner, third);
+}
+
+#[test]
+fn two_players_fight_one_match() {
+    let sys = init_system(0);
+    register_players(&sys, ROOM_ID, 2);
+    assert!(!start_tournament(&sys, owner(1), ROOM_ID).main_failed());
+
+    let [first, second] = [0, 1].map(|player| ActorId::from(tmg_id(player)));
+    let room = room(&sys, ROOM_ID);
+    assert_eq!(room.rounds, vec![vec![Match::new(first, Some(second))]]);
+    assert_eq!(room.state, BattleState::Moves);
+
+    let res = surrender(&sys, owner(0), ROOM_ID);
+    assert!(replied(
+        &res,
+        owner(0),
+        BattleEvent::GameIsOver {
+            last_move: None,
+            winner: second
+        }
+    ));
+}
+
+#[test]
+fn powers_differ_within_block() {
+    let sys = init_system(0);
+    // every random power is used as is
+    let config = Config {
+        min_power: 1,
+        ..Default::default()
+    };
+    let res = sys
+        .get_program(BATTLE_ID)
+        .send(ADMIN, BattleAction::UpdateConfig(config));
+    assert!(replied(&res, ADMIN, BattleEvent::ConfigUpdated));
+    register_players(&sys, ROOM_ID, 2);
+
+    // the powers of both players are generated by the same message
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+    let room = room(&sys, ROOM_ID);
+    assert_ne!(room.players[0].power, room.players[1].power);
+}
+
+#[test]
+fn committed_seeds_must_be_revealed() {
+    let sys = init_system(0);
+    register_players(&sys, ROOM_ID, 2);
+    let seed = [1; 32];
+    // only the registered players take part in the randomness
+    assert!(commit_seed(&sys, STRANGER, ROOM_ID, seed).main_failed());
+    let res = commit_seed(&sys, owner(0), ROOM_ID, seed);
+    assert!(replied(&res, owner(0), BattleEvent::SeedCommitted));
+
+    assert!(start_tournament(&sys, owner(1), ROOM_ID).main_failed());
+    assert!(reveal_seed(&sys, owner(0), ROOM_ID, [2; 32]).main_failed());
+
+    let res = reveal_seed(&sys, owner(0), ROOM_ID, seed);
+    assert!(replied(&res, owner(0), BattleEvent::SeedRevealed));
+    assert!(!start_tournament(&sys, owner(1), ROOM_ID).main_failed());
+    assert_eq!(room(&sys, ROOM_ID).players.len(), 2);
+}
+
+#[test]
+fn unrevealed_seed_drops_player() {
+    let sys = init_system(0);
+    register_players(&sys, ROOM_ID, 3);
+    assert!(!commit_seed(&sys, owner(2), ROOM_ID, [1; 32]).main_failed());
+
+    assert!(start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+    sys.spend_blocks(REVEAL_DURATION + 1);
+
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+    let room = room(&sys, ROOM_ID);
+    assert_eq!(room.players.len(), 2);
+    assert!(!room
+        .players
+        .iter()
+        .any(|player| player.tmg_id == ActorId::from(tmg_id(2))));
+    assert!(room.random.commitments.is_empty());
+}
+
+#[test]
+fn full_room_waits_for_seeds() {
+    let sys = init_system(0);
+    register_players(&sys, ROOM_ID, MAX_PLAYERS - 1);
+    let seed = [1; 32];
+    assert!(!commit_seed(&sys, owner(0), ROOM_ID, seed).main_failed());
+
+    // the full room doesn't start with an unrevealed seed and takes no more players
+    let last = MAX_PLAYERS - 1;
+    assert!(!register(&sys, last, ROOM_ID).main_failed());
+    assert_eq!(room(&sys, ROOM_ID).state, BattleState::Registration);
+    assert!(register(&sys, MAX_PLAYERS, ROOM_ID).main_failed());
+
+    // the last revealed seed starts the tournament
+    assert!(!reveal_seed(&sys, owner(0), ROOM_ID, seed).main_failed());
+    let room = room(&sys, ROOM_ID);
+    assert_eq!(room.players.len(), MAX_PLAYERS);
+    assert_eq!(room.state, BattleState::Moves);
+}
+
+#[test]
+fn win_by_energy() {
+    let sys = init_system(0);
+    register_players(&sys, ROOM_ID, 2);
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+
+    // the player whose turn it isn't can't move
+    let [_, waiting] = fighters(&sys, ROOM_ID);
+    assert!(make_move(&sys, waiting, ROOM_ID, Move::Attack).main_failed());
+
+    let mut moves = 0;
+    loop {
+        match room(&sys, ROOM_ID).state {
+            BattleState::Moves => {
+                let [attacker, _] = fighters(&sys, ROOM_ID);
+                assert!(!make_move(&sys, attacker, ROOM_ID, Move::Attack).main_failed());
+            }
+            BattleState::Waiting => {
+                sys.spend_blocks(Config::default().time_for_update);
+            }
+            BattleState::GameIsOver => break,
+            state => panic!("The game must go on, not be in {state:?}"),
+        }
+        moves += 1;
+        assert!(moves < 100, "The match must be over");
+    }
+
+    let room = room(&sys, ROOM_ID);
+    let last_move = room.moves.last().expect("The moves are recorded");
+    assert_eq!(last_move.attacker, room.winner);
+    assert_eq!(last_move.defender_energy, 0);
+    let loser = room
+        .players
+        .iter()
+        .find(|player| player.tmg_id != room.winner)
+        .expect("The loser is registered");
+    assert_eq!(loser.energy, 0);
+}
+
+#[test]
+fn move_cooldowns() {
+    let sys = init_system(0);
+    register_players(&sys, ROOM_ID, 2);
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+    let [player, opponent] = fighters(&sys, ROOM_ID);
+
+    assert!(!make_move(&sys, player, ROOM_ID, Move::Defend).main_failed());
+    assert!(!make_move(&sys, opponent, ROOM_ID, Move::Attack).main_failed());
+    // the defense can't be repeated on the next move
+    assert!(make_move(&sys, player, ROOM_ID, Move::Defend).main_failed());
+    assert!(!make_move(&sys, player, ROOM_ID, Move::Heal).main_failed());
+
+    let room = room(&sys, ROOM_ID);
+    let player = room
+        .players
+        .iter()
+        .find(|p| owner_of(&p.tmg_id) == player)
+        .expect("The player is registered");
+    assert_eq!(player.cooldowns.get(&Move::Defend).copied(), Some(0));
+    assert_eq!(player.cooldowns.get(&Move::Heal).copied(), Some(3));
+}
+
+#[test]
+fn win_by_surrender() {
+    let sys = init_system(0);
+    register_players(&sys, ROOM_ID, 2);
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+
+    // only the fighters can surrender
+    assert!(surrender(&sys, STRANGER, ROOM_ID).main_failed());
+
+    let res = surrender(&sys, owner(0), ROOM_ID);
+    assert!(replied(
+        &res,
+        owner(0),
+        BattleEvent::GameIsOver {
+            last_move: None,
+            winner: tmg_id(1).into()
+        }
+    ));
+}
+
+#[test]
+fn win_by_turn_timeout() {
+    let sys = init_system(0);
+    register_players(&sys, ROOM_ID, 2);
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+    let [idle, opponent] = fighters(&sys, ROOM_ID);
+
+    // the turn passes to the opponent who doesn't move either
+    sys.spend_blocks(TURN_DURATION + 1);
+    assert_eq!(fighters(&sys, ROOM_ID), [opponent, idle]);
+    sys.spend_blocks(TURN_DURATION + 1);
+    assert_eq!(room(&sys, ROOM_ID).state, BattleState::Moves);
+
+    // the second missed turn forfeits the match
+    sys.spend_blocks(TURN_DURATION + 1);
+    let room = room(&sys, ROOM_ID);
+    assert_eq!(room.state, BattleState::GameIsOver);
+    assert_eq!(room.winner, tmg_of(opponent));
+}
+
+#[test]
+fn claim_timeout_checks() {
+    let sys = init_system(0);
+    register_players(&sys, ROOM_ID, 2);
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+    let [idle, opponent] = fighters(&sys, ROOM_ID);
+    let turn_number = room(&sys, ROOM_ID).turn_number;
+
+    // the time for the move isn't over
+    assert!(claim_timeout(&sys, opponent, ROOM_ID, turn_number).main_failed());
+    // the player can't skip their own turn
+    assert!(claim_timeout(&sys, idle, ROOM_ID, turn_number).main_failed());
+
+    assert!(!make_move(&sys, idle, ROOM_ID, Move::Attack).main_failed());
+    // the turn is over
+    assert!(claim_timeout(&sys, opponent, ROOM_ID, turn_number).main_failed());
+}
+
+#[test]
+fn turns_continue_after_update() {
+    let sys = init_system(0);
+    let config = Config {
+        max_steps_for_round: 1,
+        ..Default::default()
+    };
+    let res = sys
+        .get_program(BATTLE_ID)
+        .send(ADMIN, BattleAction::UpdateConfig(config));
+    assert!(replied(&res, ADMIN, BattleEvent::ConfigUpdated));
+    register_players(&sys, ROOM_ID, 2);
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+
+    let [first, second] = fighters(&sys, ROOM_ID);
+    assert!(!make_move(&sys, first, ROOM_ID, Move::Defend).main_failed());
+    assert!(!make_move(&sys, second, ROOM_ID, Move::Defend).main_failed());
+    assert!(!make_move(&sys, first, ROOM_ID, Move::Heal).main_failed());
+    assert_eq!(room(&sys, ROOM_ID).state, BattleState::Waiting);
+
+    // the update is a delayed message that schedules the turn timeout in turn
+    let res = sys.spend_blocks(config.time_for_update);
+    assert!(res.iter().all(|res| !res.main_failed()));
+    assert_eq!(room(&sys, ROOM_ID).state, BattleState::Moves);
+
+    // the timeouts chained by the delayed messages skip the turns of both players
+    let [idle, opponent] = fighters(&sys, ROOM_ID);
+    let res = sys.spend_blocks(TURN_DURATION + 1);
+    assert!(res.iter().all(|res| !res.main_failed()));
+    assert_eq!(fighters(&sys, ROOM_ID), [opponent, idle]);
+    let res = sys.spend_blocks(TURN_DURATION + 1);
+    assert!(res.iter().all(|res| !res.main_failed()));
+    assert_eq!(fighters(&sys, ROOM_ID), [idle, opponent]);
+
+    let res = make_move(&sys, idle, ROOM_ID, Move::Attack);
+    assert!(!res.main_failed());
+    assert_eq!(room(&sys, ROOM_ID).state, BattleState::Moves);
+}
+
+#[test]
+fn entry_fee_and_payout() {
+    let sys = init_system(ENTRY_FEE);
+    register_players(&sys, ROOM_ID, 2);
+    assert_eq!(balance_of(&sys, owner(0)), BALANCE - ENTRY_FEE);
+    assert_eq!(balance_of(&sys, BATTLE_ID), 2 * ENTRY_FEE);
+    assert_eq!(room(&sys, ROOM_ID).prize_pool, 2 * ENTRY_FEE);
+
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+    assert!(!surrender(&sys, owner(0), ROOM_ID).main_failed());
+
+    let arena_fee = 2 * ENTRY_FEE * ARENA_FEE_PERCENT as u128 / 100;
+    assert_eq!(balance_of(&sys, FEE_COLLECTOR), arena_fee);
+    assert_eq!(balance_of(&sys, owner(1)), BALANCE + ENTRY_FEE - arena_fee);
+    assert_eq!(balance_of(&sys, BATTLE_ID), 0);
+    let room = room(&sys, ROOM_ID);
+    assert_eq!(room.prize_pool, 0);
+    assert_eq!(room.arena_fee, 0);
+}
+
+#[test]
+fn cancel_game_refunds_fees() {
+    let sys = init_system(ENTRY_FEE);
+    register_players(&sys, ROOM_ID, 2);
+
+    let battle = sys.get_program(BATTLE_ID);
+    // only the admin or the room creator can cancel the game
+    let res = battle.send(STRANGER, BattleAction::CancelGame { room_id: ROOM_ID });
+    assert!(res.main_failed());
+
+    let res = battle.send(owner(0), BattleAction::CancelGame { room_id: ROOM_ID });
+    assert!(replied(&res, owner(0), BattleEvent::GameCancelled));
+    assert_eq!(balance_of(&sys, owner(0)), BALANCE);
+    assert_eq!(balance_of(&sys, owner(1)), BALANCE);
+    assert_eq!(balance_of(&sys, BATTLE_ID), 0);
+    let room = room(&sys, ROOM_ID);
+    assert!(room.players.is_empty());
+    assert!(room.stakes.is_empty());
+    assert_eq!(room.prize_pool, 0);
+}
+
+#[test]
+fn unrevealed_seed_costs_entry_fee() {
+    let sys = init_system(ENTRY_FEE);
+    register_players(&sys, ROOM_ID, 3);
+    assert!(!commit_seed(&sys, owner(2), ROOM_ID, [1; 32]).main_failed());
+    sys.spend_blocks(REVEAL_DURATION + 1);
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+
+    // the fee of the dropped player goes to the winner
+    let room = room(&sys, ROOM_ID);
+    assert_eq!(room.players.len(), 2);
+    assert_eq!(room.prize_pool, 3 * ENTRY_FEE);
+    assert!(!surrender(&sys, owner(0), ROOM_ID).main_failed());
+    let arena_fee = 3 * ENTRY_FEE * ARENA_FEE_PERCENT as u128 / 100;
+    assert_eq!(
+        balance_of(&sys, owner(1)),
+        BALANCE + 2 * ENTRY_FEE - arena_fee
+    );
+    assert_eq!(balance_of(&sys, owner(2)), BALANCE - ENTRY_FEE);
+}
+
+#[test]
+fn reset_refunds_dropped_players() {
+    let sys = init_system(ENTRY_FEE);
+    register_players(&sys, ROOM_ID, 3);
+    assert!(!commit_seed(&sys, owner(2), ROOM_ID, [1; 32]).main_failed());
+    sys.spend_blocks(REVEAL_DURATION + 1);
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+
+    let battle = sys.get_program(BATTLE_ID);
+    let res = battle.send(ADMIN, BattleAction::ResetGame { room_id: ROOM_ID });
+    assert!(replied(&res, ADMIN, BattleEvent::GameReset));
+    for player in 0..3 {
+        assert_eq!(balance_of(&sys, owner(player)), BALANCE);
+    }
+    let room = room(&sys, ROOM_ID);
+    assert!(room.stakes.is_empty());
+    assert_eq!(room.prize_pool, 0);
+
+    // no tokens are left in the room
+    let res = battle.send(owner(0), BattleAction::CloseRoom { room_id: ROOM_ID });
+    assert!(replied(
+        &res,
+        owner(0),
+        BattleEvent::RoomClosed { room_id: ROOM_ID }
+    ));
+}
+
+#[test]
+fn claim_refund_without_refund() {
+    let sys = init_system(ENTRY_FEE);
+    register_players(&sys, ROOM_ID, 1);
+    let res = sys
+        .get_program(BATTLE_ID)
+        .send(owner(0), BattleAction::ClaimRefund);
+    assert!(res.main_failed());
+    assert!(battle_state(&sys).refunds.is_empty());
+}
+
+#[test]
+fn rating_change() {
+    let sys = init_system(0);
+    register_players(&sys, ROOM_ID, 2);
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+    assert!(!surrender(&sys, owner(0), ROOM_ID).main_failed());
+
+    // the ratings are equal, so the winner takes a half of the K-factor from the loser
+    assert_eq!(
+        stats_of(&sys, 1),
+        PlayerStats {
+            wins: 1,
+            losses: 0,
+            games: 1,
+            rating: 1_216,
+        }
+    );
+    assert_eq!(
+        stats_of(&sys, 0),
+        PlayerStats {
+            wins: 0,
+            losses: 1,
+            games: 1,
+            rating: 1_184,
+        }
+    );
+    assert_eq!(
+        battle_state(&sys).leaderboard(1),
+        vec![(tmg_id(1).into(), stats_of(&sys, 1))]
+    );
+}
+
+#[test]
+fn rooms_are_isolated() {
+    let sys = init_system(0);
+    let res = create_room(&sys, owner(2));
+    assert!(replied(
+        &res,
+        owner(2),
+        BattleEvent::RoomCreated { room_id: 1 }
+    ));
+
+    register_players(&sys, ROOM_ID, 2);
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+    assert!(!register(&sys, 2, 1).main_failed());
+
+    // the Tamagotchi fights in one game at a time
+    assert!(register(&sys, 0, 1).main_failed());
+    // the moves are made in the room of the match
+    let [attacker, _] = fighters(&sys, ROOM_ID);
+    assert!(make_move(&sys, attacker, 1, Move::Attack).main_failed());
+
+    assert!(!surrender(&sys, owner(0), ROOM_ID).main_failed());
+    assert_eq!(room(&sys, ROOM_ID).state, BattleState::GameIsOver);
+    let other_room = room(&sys, 1);
+    assert_eq!(other_room.state, BattleState::Registration);
+    assert_eq!(other_room.players.len(), 1);
+
+    // the Tamagotchi is free once its game is over
+    assert!(!register(&sys, 0, 1).main_failed());
+    assert_eq!(room(&sys, 1).players.len(), 2);
+}
+
+#[test]
+fn private_room() {
+    let sys = init_system(0);
+    let battle = sys.get_program(BATTLE_ID);
+    let res = battle.send(
+        owner(0),
+        BattleAction::CreateRoom {
+            invited: Some([ActorId::from(owner(1))].into()),
+        },
+    );
+    assert!(replied(
+        &res,
+        owner(0),
+        BattleEvent::RoomCreated { room_id: 1 }
+    ));
+
+    assert!(!register(&sys, 0, 1).main_failed());
+    assert!(!register(&sys, 1, 1).main_failed());
+    assert!(register(&sys, 2, 1).main_failed());
+
+    // only the room creator can invite
+    let invite = || BattleAction::Invite {
+        room_id: 1,
+        players: [ActorId::from(owner(2))].into(),
+    };
+    assert!(battle.send(owner(1), invite()).main_failed());
+    let res = battle.send(owner(0), invite());
+    assert!(replied(&res, owner(0), BattleEvent::Invited));
+    assert!(!register(&sys, 2, 1).main_failed());
+    assert_eq!(room(&sys, 1).players.len(), 3);
+}
+
+#[test]
+fn close_room_after_game() {
+    let sys = init_system(ENTRY_FEE);
+    register_players(&sys, ROOM_ID, 2);
+
+    // the registered players must be refunded first
+    assert!(close_room(&sys, owner(0), ROOM_ID).main_failed());
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+    // the game is being played
+    assert!(close_room(&sys, owner(0), ROOM_ID).main_failed());
+    assert!(!surrender(&sys, owner(0), ROOM_ID).main_failed());
+
+    // only the admin or the room creator can close the room
+    assert!(close_room(&sys, owner(1), ROOM_ID).main_failed());
+    let res = close_room(&sys, owner(0), ROOM_ID);
+    assert!(replied(
+        &res,
+        owner(0),
+        BattleEvent::RoomClosed { room_id: ROOM_ID }
+    ));
+    assert!(battle_state(&sys).rooms.is_empty());
+
+    // the pending turn timeouts of the closed room are ignored
+    let res = sys.spend_blocks(TURN_DURATION + 1);
+    assert!(res.iter().all(|res| !res.main_failed()));
+}
+
+#[test]
+fn close_empty_room() {
+    let sys = init_system(0);
+    let res = create_room(&sys, owner(1));
+    assert!(replied(
+        &res,
+        owner(1),
+        BattleEvent::RoomCreated { room_id: 1 }
+    ));
+
+    let res = close_room(&sys, ADMIN, 1);
+    assert!(replied(&res, ADMIN, BattleEvent::RoomClosed { room_id: 1 }));
+    assert!(register(&sys, 1, 1).main_failed());
+    // the closed room doesn't affect the other rooms
+    assert_eq!(battle_state(&sys).rooms.len(), 1);
+    register_players(&sys, ROOM_ID, 1);
+}
+
+#[test]
+fn wrong_registrations() {
+    let sys = init_system(0);
+    register_players(&sys, ROOM_ID, 1);
+
+    // the Tamagotchi is already registered
+    assert!(register(&sys, 0, ROOM_ID).main_failed());
+    // only the owner can register the Tamagotchi
+    let battle = sys.get_program(BATTLE_ID);
+    let res = battle.send(
+        owner(1),
+        BattleAction::Register {
+            room_id: ROOM_ID,
+            tmg_id: tmg_id(2).into(),
+        },
+    );
+    assert!(res.main_failed());
+    // hungry Tamagotchis can't fight
+    let res = battle.send(
+        STRANGER,
+        BattleAction::Register {
+            room_id: ROOM_ID,
+            tmg_id: HUNGRY_TMG_ID.into(),
+        },
+    );
+    assert!(res.main_failed());
+    // the room doesn't exist
+    assert!(register(&sys, 1, 1).main_failed());
+
+    assert_eq!(room(&sys, ROOM_ID).players.len(), 1);
+}
+
+#[test]
+fn tamagotchi_without_state_check() {
+    let sys = init_system(0);
+    let res = sys.get_program(BATTLE_ID).send(
+        STRANGER,
+        BattleAction::Register {
+            room_id: ROOM_ID,
+            tmg_id: UNCHECKED_TMG_ID.into(),
+        },
+    );
+    assert!(replied(
+        &res,
+        STRANGER,
+        BattleEvent::Registered {
+            tmg_id: UNCHECKED_TMG_ID.into()
+        }
+    ));
+}
+
+#[test]
+fn match_results_give_experience() {
+    let sys = init_system(0);
+    register_players(&sys, ROOM_ID, 2);
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+    assert!(!surrender(&sys, owner(0), ROOM_ID).main_failed());
+
+    assert_eq!(experience_of(&sys, tmg_id(1)), 20);
+    assert_eq!(experience_of(&sys, tmg_id(0)), 5);
+}
+
+#[test]
+fn tamagotchi_without_level() {
+    let sys = init_system(0);
+    let res = sys.get_program(BATTLE_ID).send(
+        STRANGER,
+        BattleAction::Register {
+            room_id: ROOM_ID,
+            tmg_id: UNLEVELED_TMG_ID.into(),
+        },
+    );
+    assert!(replied(
+        &res,
+        STRANGER,
+        BattleEvent::Registered {
+            tmg_id: UNLEVELED_TMG_ID.into()
+        }
+    ));
+    assert_eq!(room(&sys, ROOM_ID).players[0].level, 0);
+}
+
+#[test]
+fn pause() {
+    let sys = init_system(0);
+    let battle = sys.get_program(BATTLE_ID);
+
+    // only the admin can pause the arena
+    assert!(battle.send(STRANGER, BattleAction::Pause).main_failed());
+    set_paused(&sys, true);
+
+    assert!(create_room(&sys, owner(1)).main_failed());
+    assert!(register(&sys, 0, ROOM_ID).main_failed());
+
+    set_paused(&sys, false);
+    register_players(&sys, ROOM_ID, 1);
+}
+
+#[test]
+fn paused_reveal_doesnt_start_tournament() {
+    let sys = init_system(0);
+    // the seed is committed before the room is full
+    register_players(&sys, ROOM_ID, MAX_PLAYERS - 1);
+    let seed = [1; 32];
+    assert!(!commit_seed(&sys, owner(0), ROOM_ID, seed).main_failed());
+    assert!(!register(&sys, MAX_PLAYERS - 1, ROOM_ID).main_failed());
+
+    set_paused(&sys, true);
+    assert!(!reveal_seed(&sys, owner(0), ROOM_ID, seed).main_failed());
+    assert_eq!(room(&sys, ROOM_ID).state, BattleState::Registration);
+    assert!(start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+
+    set_paused(&sys, false);
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+    assert_eq!(room(&sys, ROOM_ID).state, BattleState::Moves);
+}
+
+#[test]
+fn reset_game() {
+    let sys = init_system(ENTRY_FEE);
+    register_players(&sys, ROOM_ID, 2);
+    assert!(!start_tournament(&sys, owner(0), ROOM_ID).main_failed());
+
+    let battle = sys.get_program(BATTLE_ID);
+    // only the admin can reset the game
+    let res = battle.send(owner(0), BattleAction::ResetGame { room_id: ROOM_ID });
+    assert!(res.main_failed());
+
+    let res = battle.send(ADMIN, BattleAction::ResetGame { room_id: ROOM_ID });
+    assert!(replied(&res, ADMIN, BattleEvent::GameReset));
+    let room = room(&sys, ROOM_ID);
+    assert_eq!(room.state, BattleState::Registration);
+    assert!(room.players.is_empty());
+    assert!(room.rounds.is_empty());
+    assert_eq!(balance_of(&sys, owner(0)), BALANCE);
+    assert_eq!(balance_of(&sys, owner(1)), BALANCE);
+
+    // the stopped game doesn't interfere with the next one
+    register_players(&sys, ROOM_ID, 2);
+    sys.spend_blocks(TURN_DURATION + 1);
+    assert_eq!(utils::room(&sys, ROOM_ID).state, BattleState::Registration);
+}
@@ -0,0 +1,429 @@
+#![allow(dead_code)]
+
+use battle_io::*;
+use blake2_rfc::blake2b::blake2b;
+use ft_main_io::{FTokenAction, FTokenEvent, LogicAction};
+use gstd::ActorId;
+use gtest::{Log, Program, RunResult, System, WasmProgram};
+use parity_scale_codec::{Decode, Encode};
+use std::collections::BTreeMap;
+use store_io::{Effects, StoreAction, StoreEvent, TamagotchiId};
+use tmg_io::{TmgAction, TmgEvent};
+
+pub const BATTLE_ID: u64 = 1;
+pub const FT_ID: u64 = 2;
+pub const STORE_ID: u64 = 3;
+pub const ADMIN: u64 = 98;
+pub const FEE_COLLECTOR: u64 = 99;
+pub const STRANGER: u64 = 200;
+// the player `i` is the account `FIRST_OWNER + i` with the Tamagotchi `FIRST_TMG_ID + i`
+pub const FIRST_OWNER: u64 = 100;
+pub const FIRST_TMG_ID: u64 = 10;
+// the number of players a room takes in the program,
+// one more player is mocked to overfill the room
+pub const MAX_PLAYERS: usize = 16;
+pub const HUNGRY_TMG_ID: u64 = 50;
+pub const UNCHECKED_TMG_ID: u64 = 51;
+pub const UNLEVELED_TMG_ID: u64 = 52;
+pub const BALANCE: u128 = 1_000_000;
+pub const ENTRY_FEE: u128 = 1_000;
+pub const ARENA_FEE_PERCENT: u8 = 10;
+pub const ROOM_ID: RoomId = 0;
+// the number of blocks given for a move in the program
+pub const TURN_DURATION: u32 = 20;
+// the number of blocks given to reveal the committed seeds in the program
+pub const REVEAL_DURATION: u32 = 100;
+
+pub fn owner(player: usize) -> u64 {
+    FIRST_OWNER + player as u64
+}
+
+pub fn tmg_id(player: usize) -> u64 {
+    FIRST_TMG_ID + player as u64
+}
+
+/// Tamagotchi with the battle part of the Tamagotchi interface.
+#[derive(Debug, Default)]
+pub struct TamagotchiMock {
+    owner: ActorId,
+    hungry: bool,
+    // like the Tamagotchis deployed before the battle
+    without_state_check: bool,
+    without_level: bool,
+    experience: u64,
+}
+
+impl WasmProgram for TamagotchiMock {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let action =
+            TmgAction::decode(&mut &payload[..]).map_err(|_| "Unable to decode `TmgAction`")?;
+        let event = match action {
+            TmgAction::Owner => TmgEvent::Owner(self.owner),
+            TmgAction::CheckState if self.without_state_check => {
+                return Err("Unexpected `TmgAction`")
+            }
+            TmgAction::CheckState if self.hungry => TmgEvent::FeedMe,
+            TmgAction::CheckState => TmgEvent::Fed,
+            TmgAction::Level if self.without_level => return Err("Unexpected `TmgAction`"),
+            TmgAction::Level => TmgEvent::Level(tmg_io::level(self.experience)),
+            TmgAction::BattleResult { experience, .. } => {
+                self.experience += experience;
+                TmgEvent::BattleResultApplied {
+                    experience: self.experience,
+                    level: tmg_io::level(self.experience),
+                }
+            }
+            _ => return Err("Unexpected `TmgAction`"),
+        };
+        Ok(Some(event.encode()))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Ok(self.experience.encode())
+    }
+}
+
+/// Store where the Tamagotchis have no attributes equipped.
+#[derive(Debug, Default)]
+pub struct StoreMock;
+
+impl WasmProgram for StoreMock {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let action =
+            StoreAction::decode(&mut &payload[..]).map_err(|_| "Unable to decode `StoreAction`")?;
+        let event = match action {
+            StoreAction::GetEquipped { .. } => StoreEvent::Equipped {
+                equipped: BTreeMap::new(),
+            },
+            StoreAction::GetEffects { .. } => StoreEvent::Effects {
+                effects: Effects::default(),
+            },
+            _ => return Err("Unexpected `StoreAction`"),
+        };
+        Ok(Some(event.encode()))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Ok(Vec::new())
+    }
+}
+
+/// Token ledger. A repeated transaction gets the result of its first execution,
+/// so the battle can rerun an interrupted transfer without paying twice.
+#[derive(Debug, Default)]
+pub struct TokenMock {
+    balances: BTreeMap<ActorId, u128>,
+    executed: BTreeMap<u64, bool>,
+}
+
+impl TokenMock {
+    fn execute(&mut self, sender: ActorId, recipient: ActorId, amount: u128) -> bool {
+        match self.balances.get(&sender) {
+            Some(balance) if *balance >= amount => {
+                self.balances.insert(sender, balance - amount);
+                *self.balances.entry(recipient).or_default() += amount;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl WasmProgram for TokenMock {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let action = FTokenAction::decode(&mut &payload[..])
+            .map_err(|_| "Unable to decode `FTokenAction`")?;
+        let FTokenAction::Message {
+            transaction_id,
+            payload:
+                LogicAction::Transfer {
+                    sender,
+                    recipient,
+                    amount,
+                },
+        } = action
+        else {
+            return Err("Unexpected `FTokenAction`");
+        };
+        let succeeded = match self.executed.get(&transaction_id) {
+            Some(succeeded) => *succeeded,
+            None => {
+                let succeeded = self.execute(sender, recipient, amount);
+                self.executed.insert(transaction_id, succeeded);
+                succeeded
+            }
+        };
+        let event = if succeeded {
+            FTokenEvent::Ok
+        } else {
+            FTokenEvent::Err
+        };
+        Ok(Some(event.encode()))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Ok(self.balances.encode())
+    }
+}
+
+/// Deploys the battle with the mocks it talks to
+/// and opens the public room `ROOM_ID` created by the first player.
+pub fn init_system(entry_fee: u128) -> System {
+    let sys = System::new();
+    sys.init_logger();
+
+    let battle = Program::current(&sys);
+    let res = battle.send(
+        ADMIN,
+        InitBattle {
+            tmg_store_id: STORE_ID.into(),
+            ft_contract_id: FT_ID.into(),
+            entry_fee,
+            fee_collector: FEE_COLLECTOR.into(),
+            arena_fee_percent: ARENA_FEE_PERCENT,
+            config: Config::default(),
+        },
+    );
+    assert!(!res.main_failed());
+
+    Program::mock_with_id(&sys, STORE_ID, StoreMock);
+    Program::mock_with_id(
+        &sys,
+        FT_ID,
+        TokenMock {
+            balances: (0..=MAX_PLAYERS)
+                .map(|player| (owner(player).into(), BALANCE))
+                .collect(),
+            ..Default::default()
+        },
+    );
+    for player in 0..=MAX_PLAYERS {
+        Program::mock_with_id(
+            &sys,
+            tmg_id(player),
+            TamagotchiMock {
+                owner: owner(player).into(),
+                ..Default::default()
+            },
+        );
+    }
+    Program::mock_with_id(
+        &sys,
+        HUNGRY_TMG_ID,
+        TamagotchiMock {
+            owner: STRANGER.into(),
+            hungry: true,
+            ..Default::default()
+        },
+    );
+    Program::mock_with_id(
+        &sys,
+        UNCHECKED_TMG_ID,
+        TamagotchiMock {
+            owner: STRANGER.into(),
+            without_state_check: true,
+            ..Default::default()
+        },
+    );
+    Program::mock_with_id(
+        &sys,
+        UNLEVELED_TMG_ID,
+        TamagotchiMock {
+            owner: STRANGER.into(),
+            without_level: true,
+            experience: 300,
+            ..Default::default()
+        },
+    );
+
+    let res = battle.send(owner(0), BattleAction::CreateRoom { invited: None });
+    assert!(replied(
+        &res,
+        owner(0),
+        BattleEvent::RoomCreated { room_id: ROOM_ID }
+    ));
+
+    sys
+}
+
+pub fn create_room(sys: &System, creator: u64) -> RunResult {
+    sys.get_program(BATTLE_ID)
+        .send(creator, BattleAction::CreateRoom { invited: None })
+}
+
+pub fn close_room(sys: &System, account: u64, room_id: RoomId) -> RunResult {
+    sys.get_program(BATTLE_ID)
+        .send(account, BattleAction::CloseRoom { room_id })
+}
+
+pub fn register(sys: &System, player: usize, room_id: RoomId) -> RunResult {
+    sys.get_program(BATTLE_ID).send(
+        owner(player),
+        BattleAction::Register {
+            room_id,
+            tmg_id: tmg_id(player).into(),
+        },
+    )
+}
+
+/// Registers the players from the first one up to `players` in the room.
+pub fn register_players(sys: &System, room_id: RoomId, players: usize) {
+    for player in 0..players {
+        let res = register(sys, player, room_id);
+        assert!(replied(
+            &res,
+            owner(player),
+            BattleEvent::Registered {
+                tmg_id: tmg_id(player).into()
+            }
+        ));
+    }
+}
+
+pub fn start_tournament(sys: &System, account: u64, room_id: RoomId) -> RunResult {
+    sys.get_program(BATTLE_ID)
+        .send(account, BattleAction::StartTournament { room_id })
+}
+
+/// Commits the hash of the seed the way the program checks it.
+pub fn commit_seed(sys: &System, account: u64, room_id: RoomId, seed: [u8; 32]) -> RunResult {
+    let hash = blake2b(32, &[], &seed)
+        .as_bytes()
+        .try_into()
+        .expect("The hash is 32 bytes long");
+    sys.get_program(BATTLE_ID)
+        .send(account, BattleAction::CommitSeed { room_id, hash })
+}
+
+pub fn reveal_seed(sys: &System, account: u64, room_id: RoomId, seed: [u8; 32]) -> RunResult {
+    sys.get_program(BATTLE_ID)
+        .send(account, BattleAction::RevealSeed { room_id, seed })
+}
+
+pub fn make_move(sys: &System, account: u64, room_id: RoomId, kind: Move) -> RunResult {
+    sys.get_program(BATTLE_ID)
+        .send(account, BattleAction::MakeMove { room_id, kind })
+}
+
+pub fn surrender(sys: &System, account: u64, room_id: RoomId) -> RunResult {
+    sys.get_program(BATTLE_ID)
+        .send(account, BattleAction::Surrender { room_id })
+}
+
+pub fn claim_timeout(sys: &System, account: u64, room_id: RoomId, turn_number: u64) -> RunResult {
+    sys.get_program(BATTLE_ID).send(
+        account,
+        BattleAction::TurnTimeout {
+            room_id,
+            turn_number,
+        },
+    )
+}
+
+pub fn set_paused(sys: &System, paused: bool) {
+    let (action, event) = if paused {
+        (BattleAction::Pause, BattleEvent::Paused)
+    } else {
+        (BattleAction::Unpause, BattleEvent::Unpaused)
+    };
+    let res = sys.get_program(BATTLE_ID).send(ADMIN, action);
+    assert!(replied(&res, ADMIN, event));
+}
+
+pub fn battle_state(sys: &System) -> Battle {
+    sys.get_program(BATTLE_ID)
+        .read_state()
+        .expect("Unable to read the battle state")
+}
+
+pub fn room(sys: &System, room_id: RoomId) -> Room {
+    battle_state(sys)
+        .rooms
+        .remove(&room_id)
+        .expect("The room doesn't exist")
+}
+
+pub fn stats_of(sys: &System, player: usize) -> PlayerStats {
+    battle_state(sys)
+        .stats_of(&tmg_id(player).into())
+        .expect("The Tamagotchi hasn't played")
+}
+
+/// Returns the accounts of the current match fighters, the one whose turn it is first.
+pub fn fighters(sys: &System, room_id: RoomId) -> [u64; 2] {
+    let room = room(sys, room_id);
+    let (first, second) = room
+        .current_match()
+        .expect("No match is being fought")
+        .tamagotchis;
+    let second = second.expect("Matches with a bye aren't fought");
+    let mut fighters = [owner_of(&first), owner_of(&second)];
+    fighters.rotate_left(room.current_turn as usize);
+    fighters
+}
+
+pub fn tmg_of(account: u64) -> ActorId {
+    tmg_id((account - FIRST_OWNER) as usize).into()
+}
+
+pub fn owner_of(tmg: &TamagotchiId) -> u64 {
+    let player = (0..=MAX_PLAYERS)
+        .find(|player| ActorId::from(tmg_id(*player)) == *tmg)
+        .expect("Unknown Tamagotchi");
+    owner(player)
+}
+
+pub fn balance_of(sys: &System, account: u64) -> u128 {
+    let balances: BTreeMap<ActorId, u128> = sys
+        .get_program(FT_ID)
+        .read_state()
+        .expect("Unable to read the token state");
+    balances.get(&account.into()).copied().unwrap_or_default()
+}
+
+pub fn experience_of(sys: &System, tmg_id: u64) -> u64 {
+    sys.get_program(tmg_id)
+        .read_state()
+        .expect("Unable to read the Tamagotchi state")
+}
+
+/// Checks that the battle replied to the account with the given event.
+pub fn replied(res: &RunResult, account: u64, event: BattleEvent) -> bool {
+    res.contains(&Log::builder().dest(account).payload(event))
+}
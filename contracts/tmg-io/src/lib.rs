@@ -3,6 +3,9 @@ use gmeta::{InOut, Metadata};
 use gstd::{prelude::*, ActorId, ReservationId};
 use store_io::{AttributeId, TransactionId};
 
+/// The experience a Tamagotchi needs to gain each next level.
+pub const EXPERIENCE_PER_LEVEL: u64 = 100;
+
 pub struct ProgramMetadata;
 
 impl Metadata for ProgramMetadata {
@@ -32,6 +35,12 @@ pub struct Tamagotchi {
     pub reservations: Vec<ReservationId>,
 }
 
+/// Returns the level of a Tamagotchi with the given experience.
+/// The Tamagotchi keeps the experience gained with `BattleResult` and replies to `Level` with it.
+pub fn level(experience: u64) -> u32 {
+    (experience / EXPERIENCE_PER_LEVEL).min(u32::MAX as u64) as u32
+}
+
 #[derive(Encode, Decode, TypeInfo)]
 pub enum TmgAction {
     Name,
@@ -51,12 +60,27 @@ pub enum TmgAction {
         store_id: ActorId,
         attribute_id: AttributeId,
     },
+    /// Replies with `FeedMe` or `WantToSleep` if the Tamagotchi is hungry or tired
+    /// (the battle doesn't register such Tamagotchis).
+    /// The battle registers the Tamagotchis that don't handle it.
     CheckState,
     ReserveGas {
         reservation_amount: u64,
         duration: u32,
     },
     Owner,
+    /// Applies the result of a battle match: decreases `fed` and `rested`
+    /// and adds the experience. Sent by the battle program the Tamagotchi fought in
+    /// without waiting for the reply.
+    /// The Tamagotchi must apply it only from the battle program its owner trusts,
+    /// otherwise anyone could starve it or raise its level.
+    BattleResult {
+        won: bool,
+        fed_drain: u64,
+        rested_drain: u64,
+        experience: u64,
+    },
+    Level,
 }
 
 #[derive(Encode, Decode, TypeInfo)]
@@ -81,4 +105,6 @@ pub enum TmgEvent {
     MakeReservation,
     GasReserved,
     Owner(ActorId),
+    BattleResultApplied { experience: u64, level: u32 },
+    Level(u32),
 }
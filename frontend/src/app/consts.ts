@@ -14,6 +14,7 @@ export const ENV = {
   store: import.meta.env.VITE_STORE_ADDRESS as HexString,
   balance: import.meta.env.VITE_FT_ADDRESS as HexString,
   battle: import.meta.env.VITE_BATTLE_ADDRESS as HexString,
+  battleRoomId: (import.meta.env.VITE_BATTLE_ROOM_ID || '0') as string,
   NODE: import.meta.env.VITE_NODE_ADDRESS as string,
 };
 
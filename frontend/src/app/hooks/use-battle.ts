@@ -1,20 +1,53 @@
 import { TamagotchiState } from '../types/lessons'
-import { useEffect } from 'react'
-import { useSendMessage } from '@gear-js/react-hooks'
+import { useEffect, useMemo } from 'react'
+import { useSendMessage, withoutCommas } from '@gear-js/react-hooks'
 import { useProgramMetadata, useReadState } from './use-metadata'
 import metaBattle from '@/assets/meta/meta-battle.txt'
 import metaPlayer from '@/assets/meta/meta6.txt'
 import { ENV } from '@/app/consts'
 import { useBattle } from '@/app/context'
-import type { BattleStateResponse } from '@/app/types/battles'
+import type {
+  BattlePlayerResponse,
+  BattleProgramStateResponse,
+  BattleRoomResponse,
+  BattleStateResponse,
+} from '@/app/types/battles'
+
+// The fighters of the current match in the order of their turns
+function getFighters(room: BattleRoomResponse): BattlePlayerResponse[] {
+  const round = room.rounds[room.rounds.length - 1]
+  const match = round?.[+withoutCommas(room.currentMatch)]
+  if (!match) return []
+  return match.tamagotchis
+    .map((tmgId) => room.players.find((player) => player.tmgId === tmgId))
+    .filter((player): player is BattlePlayerResponse => !!player)
+}
+
+function getBattleState(
+  room?: BattleRoomResponse
+): BattleStateResponse | undefined {
+  if (!room) return undefined
+  return {
+    currentTurn: room.currentTurn,
+    players: room.state === 'Registration' ? [] : getFighters(room),
+    registered: room.players.length,
+    state: room.state,
+    steps: room.steps,
+    winner: room.winner,
+  }
+}
 
 export function useInitBattleData() {
   const { setPlayers, setBattleState } = useBattle()
 
-  const state = useReadState<BattleStateResponse>({
+  const programState = useReadState<BattleProgramStateResponse>({
     programId: ENV.battle,
     meta: metaBattle,
   }).state
+  const state = useMemo(
+    () => getBattleState(programState?.rooms[ENV.battleRoomId]),
+    [programState]
+  )
   const p1 = useReadState<TamagotchiState>({
     programId: state?.players[0]?.tmgId,
     meta: metaPlayer,
@@ -22,12 +22,35 @@ export type BattleStatesList =
   | 'Moves'
   | 'Waiting'
   | 'GameIsOver'
+  | 'Refunding'
 
-export type BattleStateResponse = {
+export type BattleMatchResponse = {
+  tamagotchis: [HexString, HexString | null]
+  winner: HexString | null
+}
+
+export type BattleRoomResponse = {
+  id: string
   currentTurn: string
   players: BattlePlayerResponse[]
   state: BattleStatesList
   steps: string
+  winner: HexString
+  rounds: BattleMatchResponse[][]
+  currentMatch: string
+}
+
+export type BattleProgramStateResponse = {
   tmgStoreId: HexString
+  rooms: Record<string, BattleRoomResponse>
+}
+
+// The room of the app with the fighters of the current match as `players`
+export type BattleStateResponse = {
+  currentTurn: string
+  players: BattlePlayerResponse[]
+  registered: number
+  state: BattleStatesList
+  steps: string
   winner: HexString
 }
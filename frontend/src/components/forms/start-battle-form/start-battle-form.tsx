@@ -2,7 +2,7 @@ import { hexRequired } from "@/app/utils/form-validations";
 import { useBattle } from "@/app/context";
 import { useBattleMessage } from "@/app/hooks/use-battle";
 import { useForm } from "@mantine/form";
-import { createTamagotchiInitial } from "@/app/consts";
+import { createTamagotchiInitial, ENV } from "@/app/consts";
 import { Button, Input } from "@gear-js/ui";
 
 const validate: Record<string, typeof hexRequired> = {
@@ -20,14 +20,19 @@ export const StartBattleForm = () => {
   const { getInputProps, errors } = form;
   const handleSubmit = form.onSubmit((values) => {
     const onSuccess = () => form.reset();
-    handleMessage({ Register: { tmg_id: values.programId } }, { onSuccess });
+    handleMessage(
+      { Register: { room_id: ENV.battleRoomId, tmg_id: values.programId } },
+      { onSuccess }
+    );
   });
+  const handleStart = () =>
+    handleMessage({ StartTournament: { room_id: ENV.battleRoomId } });
 
   return (
     <div className="space-y-10 my-auto">
       <h2 className="text-center typo-h2">Registration for Battle</h2>
       <p className="text-center text-white text-opacity-70">
-        Current players' queue: {battleState?.players.length ?? 0}
+        Current players' queue: {battleState?.registered ?? 0}
       </p>
       <form
         onSubmit={handleSubmit}
@@ -48,6 +53,15 @@ export const StartBattleForm = () => {
             disabled={Object.keys(errors).length > 0}
           />
         </div>
+        {(battleState?.registered ?? 0) > 1 && (
+          <div className="whitespace-nowrap">
+            <Button
+              text="Start Tournament"
+              color="secondary"
+              onClick={handleStart}
+            />
+          </div>
+        )}
       </form>
     </div>
   );
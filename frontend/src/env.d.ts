@@ -15,6 +15,7 @@ interface ImportMetaEnv {
   readonly VITE_STORE_ADDRESS: string;
   readonly VITE_FT_ADDRESS: string;
   readonly VITE_BATTLE_ADDRESS: string;
+  readonly VITE_BATTLE_ROOM_ID: string;
   readonly VITE_NODE_ADDRESS: string;
 }
 
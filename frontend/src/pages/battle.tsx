@@ -11,6 +11,7 @@ import { cn, getAttributesById } from "@/app/utils";
 import { SpriteIcon } from '@/components/ui/sprite-icon'
 import { TamagotchiBattleInfoCard } from '@/components/tamagotchi/tamagotchi-battle-info-card'
 import { StartBattleForm } from '@/components/forms/start-battle-form'
+import { ENV } from '@/app/consts'
 
 export default function Battle() {
   const { account } = useAccount()
@@ -28,7 +29,7 @@ export default function Battle() {
     if (battle?.state === 'GameIsOver') {
       setIsPending(true)
       sendMessage(
-        { StartNewGame: null },
+        { StartNewGame: { room_id: ENV.battleRoomId } },
         {
           onSuccess: () => {
             setBattleState(undefined)
@@ -40,7 +41,10 @@ export default function Battle() {
     }
     if (battle?.state === 'Moves') {
       setIsPending(true)
-      sendMessage({ MakeMove: null }, { onError, onSuccess })
+      sendMessage(
+        { MakeMove: { room_id: ENV.battleRoomId, kind: 'Attack' } },
+        { onError, onSuccess }
+      )
     }
   }
 